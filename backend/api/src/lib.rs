@@ -22,7 +22,68 @@ pub async fn serve<T: crate::types::API>(api: T, address: &str) -> Result<(), st
         let api = api1;
         let ordering = __query.get("ordering").map(|str| serde_json::from_str(str).ok()).flatten();
         let Some(ordering) = ordering else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
-        let result = <T as crate::types::API>::get_server_list(&api, ordering, ).await;
+        let direction = __query.get("direction").map(|str| serde_json::from_str(str).ok()).flatten();
+        let online_only = __query.get("online_only").map(|str| serde_json::from_str(str).ok()).flatten();
+        let min_players = __query.get("min_players").map(|str| serde_json::from_str(str).ok()).flatten();
+        let max_players = __query.get("max_players").map(|str| serde_json::from_str(str).ok()).flatten();
+        let version = __query.get("version").map(|str| serde_json::from_str(str).ok()).flatten();
+        let protocol = __query.get("protocol").map(|str| serde_json::from_str(str).ok()).flatten();
+        let edition = __query.get("edition").map(|str| serde_json::from_str(str).ok()).flatten();
+        let tags = __query.get("tags").map(|str| serde_json::from_str(str).ok()).flatten();
+        let software = __query.get("software").map(|str| serde_json::from_str(str).ok()).flatten();
+        let category = __query.get("category").map(|str| serde_json::from_str(str).ok()).flatten();
+        let language = __query.get("language").map(|str| serde_json::from_str(str).ok()).flatten();
+        let audience = __query.get("audience").map(|str| serde_json::from_str(str).ok()).flatten();
+        let locale = __query.get("locale").map(|str| serde_json::from_str(str).ok()).flatten();
+        let client_protocol = __query.get("client_protocol").map(|str| serde_json::from_str(str).ok()).flatten();
+        let limit = __query.get("limit").map(|str| serde_json::from_str(str).ok()).flatten();
+        let cursor = __query.get("cursor").map(|str| serde_json::from_str(str).ok()).flatten();
+        let result = <T as crate::types::API>::get_server_list(&api, ordering, direction, online_only, min_players, max_players, version, protocol, edition, tags, software, category, language, audience, locale, client_protocol, limit, cursor, ).await;
+        (StatusCode::OK, Json(result)).into_response()
+    }));
+
+    let api1 = api.clone();
+    router = router.route("/api/get_server_history", axum::routing::get(async move |Query(__query): Query<HashMap<String, String>>, | {
+        let api = api1;
+        let id = __query.get("id").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(id) = id else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let from = __query.get("from").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(from) = from else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let to = __query.get("to").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(to) = to else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let resolution = __query.get("resolution").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(resolution) = resolution else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let result = <T as crate::types::API>::get_server_history(&api, id, from, to, resolution, ).await;
+        (StatusCode::OK, Json(result)).into_response()
+    }));
+
+    let api1 = api.clone();
+    router = router.route("/api/search_servers", axum::routing::get(async move |Query(__query): Query<HashMap<String, String>>, | {
+        let api = api1;
+        let query = __query.get("query").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(query) = query else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let ordering = __query.get("ordering").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(ordering) = ordering else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let limit = __query.get("limit").map(|str| serde_json::from_str(str).ok()).flatten();
+        let locale = __query.get("locale").map(|str| serde_json::from_str(str).ok()).flatten();
+        let result = <T as crate::types::API>::search_servers(&api, query, ordering, limit, locale, ).await;
+        (StatusCode::OK, Json(result)).into_response()
+    }));
+
+    let api1 = api.clone();
+    router = router.route("/api/get_server", axum::routing::get(async move |Query(__query): Query<HashMap<String, String>>, | {
+        let api = api1;
+        let id = __query.get("id").map(|str| serde_json::from_str(str).ok()).flatten();
+        let Some(id) = id else { return Response::builder().status(StatusCode::BAD_REQUEST).body(Body::empty()).unwrap(); };
+        let locale = __query.get("locale").map(|str| serde_json::from_str(str).ok()).flatten();
+        let result = <T as crate::types::API>::get_server(&api, id, locale, ).await;
+        (StatusCode::OK, Json(result)).into_response()
+    }));
+
+    let api1 = api.clone();
+    router = router.route("/api/get_protocol_versions", axum::routing::get(async move |Query(__query): Query<HashMap<String, String>>, | {
+        let api = api1;
+        let result = <T as crate::types::API>::get_protocol_versions(&api, ).await;
         (StatusCode::OK, Json(result)).into_response()
     }));
 
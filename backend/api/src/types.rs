@@ -1,38 +1,442 @@
 /// サーバーリストの順序
+/// 括弧内はdirectionを省略したときの向き
+/// 同順位のサーバーは一定時間ごとに入れ替わる順に並ぶ
 #[allow(non_snake_case)]
 #[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 pub enum Ordering {
-    /// プレイヤーの多い順
+    /// プレイヤー人数 (多い順)
 
 Player,
-    /// プレイヤーの少ない順
+    /// 直近30日間の稼働率 (高い順)
 
-PlayerReverse,
+Uptime,
+    /// 7日間の平均プレイヤー人数 (多い順)
+
+WeeklyAveragePlayers,
+    /// 勢い (ある順)
+
+Trending,
+    /// 人数・平均人数・稼働率・応答時間などを合わせたスコア (高い順)
+
+Ranked,
+    /// 一定時間ごとに入れ替わるランダムな順
+    /// 同じ時間帯の中では誰が取得しても同じ順になる
+
+Shuffle,
+    /// 名前 (五十音順・アルファベット順)
+    /// 読みが登録されていれば読みで並べる
+
+Name,
+    /// pingの応答時間 (速い順)
+
+Latency,
+    /// 対応している最も新しいバージョン (新しい順)
+
+Version,
+    /// サーバーリストに追加された日時 (新しい順)
+
+RecentlyAdded,
+}
+
+/// 並び替えの向き
+/// 基準となる値がないサーバー (オフラインで応答時間がない等) は向きに関係なく最後になる
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Direction {
+    /// 小さい順・古い順・五十音順
+
+Ascending,
+    /// 大きい順・新しい順・五十音の逆順
+
+Descending,
+}
+
+/// サーバーのジャンル
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Category {
+
+Survival,
+
+Minigames,
+
+Pvp,
+
+Rpg,
+
+Creative,
+
+Modded,
+}
+
+/// 想定する年齢層
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Audience {
+    /// 全年齢
+
+Everyone,
+    /// 13歳以上
+
+Teen,
+    /// 18歳以上
+
+Adult,
+}
+
+/// Minecraftのエディション
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Edition {
+    /// Java版
+
+Java,
+    /// 統合版 (Geyser等で参加できるサーバー)
+
+Bedrock,
 }
 
 /// サーバーリストの要素
 #[allow(non_snake_case)]
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Server {
+    /// Transferパケットで転送できるか
+    /// 1.20.5以降に対応していて、転送を受け付ける (accepts-transfers=true) ならtrue
+    /// 設定で転送を実際に確かめている場合はその結果、そうでなければservers.tomlの宣言に従う
+    /// 1.20.5より前のバージョンやオンラインでないもの、転送を受け付けないと分かっているものはfalse
+    /// どちらとも分からなければnull (転送を試してよい)
+    pub transferable: Option<bool>,
+    /// client_protocolで指定したプロトコル番号のクライアントが参加できるか
+    /// pingで返されたプロトコル番号と、バージョン名から読み取れる範囲 ("1.8.x-1.21.x" など) から判断する
+    /// オフラインならfalse、client_protocolを指定していなければnull
+    pub compatible: Option<bool>,
+    /// ネットワークの入口ごとの状態
+    /// ネットワークでは、ip・port・バージョンは転送先に選ばれた入口 (is_bestがtrue) のもの
+    /// プレイヤー人数は入口ごとの合計で、プレイヤーを共有する入口は重複して数えない
+    /// ネットワークに属さないサーバーでは空
+    pub endpoints: Vec<Endpoint>,
     /// バージョン名
     pub version_name: String,
     /// 最大プレイ人数
     pub players_max: i64,
+    /// タグ
+    pub tags: Vec<String>,
+    /// サーバーのID
+    /// servers.tomlで指定されていなければホスト名とポートから作られる
+    pub id: String,
+    /// 外部のページへのリンク
+    pub links: Links,
+    /// 対応している言語 ("ja", "en-US" など)
+    pub languages: Vec<String>,
+    /// 想定する年齢層
+    /// 指定されていなければnull
+    pub audience: Option<Audience>,
+    /// ジャンル
+    pub categories: Vec<Category>,
+    /// pingの往復時間 (ミリ秒)
+    /// オフラインならnull
+    pub latency: Option<i64>,
     /// アイコンとなるアイテム名
     pub icon: String,
     /// Minecraftサーバーのポート
     pub port: i64,
     /// サーバーの名前
+    /// 言語ごとに書き分けられている場合はlocaleで指定した言語のもの
     pub name: String,
     /// オンラインかどうか
     pub is_online: bool,
+    /// 稼働状態
+    /// 再確認中(Suspect)のサーバーはis_onlineがtrueのまま
+    pub health: Health,
     /// プレイヤー人数
     pub players_online: i64,
     /// サーバーの説明欄
     /// 改行可
+    /// 言語ごとに書き分けられている場合はlocaleで指定した言語のもの
     pub description: String,
+    /// サーバーリストに追加された時刻 (UNIX時間, 秒)
+    /// servers.tomlに追加日が書かれていなければ、このAPIのサーバーが初めて確認した時刻 (実際に追加された時刻より遅いことがある)
+    pub added_at: Option<i64>,
+    /// バージョン名から推定したサーバーソフトウェア (Paper, Velocity, Vanilla など)
+    /// 推定できなければnull
+    pub software: Option<String>,
+    /// 参加できるエディション
+    pub editions: Vec<Edition>,
     /// MinecraftサーバーのIPアドレス
     pub ip: String,
+    /// 勢い
+    /// 直近24時間の平均人数が、その前の7日間の平均人数からどれだけ伸びたか (負なら減少)
+    /// 履歴が足りなければnull
+    pub trending_score: Option<f64>,
+    /// オフラインと判定された理由
+    pub offline_reason: Option<OfflineReason>,
+    /// 直近30日間の平均故障間隔 (秒)
+    /// 停止がなければnull
+    pub mtbf: Option<i64>,
+    /// 直近7日間の稼働率 (0.0〜1.0)
+    pub uptime_7d: Option<f64>,
+    /// 最後にオンラインだった時刻 (UNIX時間, 秒)
+    pub last_seen_online: Option<i64>,
+    /// 直近24時間の稼働率 (0.0〜1.0)
+    /// 履歴がなければnull
+    pub uptime_24h: Option<f64>,
+    /// 直近30日間で最も長い停止時間 (秒)
+    pub longest_outage: i64,
+    /// 直近30日間の稼働率 (0.0〜1.0)
+    pub uptime_30d: Option<f64>,
+    /// 前日までの7日間のプレイヤー人数
+    pub players_weekly: Option<PlayerStats>,
+    /// 前日 (UTC) のプレイヤー人数
+    /// 集計がなければnull
+    pub players_daily: Option<PlayerStats>,
+    /// Rankedの順序のスコアとその内訳
+    pub ranking: RankingScore,
+}
+
+/// ネットワークの入口1つ分の状態
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Endpoint {
+    /// Transferパケットで転送できるか
+    /// どちらとも分からなければnull
+    pub transferable: Option<bool>,
+    /// client_protocolで指定したプロトコル番号のクライアントが参加できるか
+    /// client_protocolを指定していなければnull
+    pub compatible: Option<bool>,
+    /// 最大プレイ人数
+    pub players_max: i64,
+    /// 転送先に選ばれたか
+    /// 稼働状態が良く、pingの往復時間が短いものが選ばれる
+    pub is_best: bool,
+    /// 入口のポート
+    pub port: i64,
+    /// 入口の名前
+    /// 言語ごとに書き分けられている場合はlocaleで指定した言語のもの
+    pub name: String,
+    /// pingの往復時間 (ミリ秒)
+    /// オフラインならnull
+    pub latency: Option<i64>,
+    /// 稼働状態
+    pub health: Health,
+    /// 参加できるエディション
+    pub editions: Vec<Edition>,
+    /// バージョン名
+    pub version_name: String,
+    /// オンラインかどうか
+    pub is_online: bool,
+    /// プレイヤー人数
+    pub players_online: i64,
+    /// 入口のIPアドレス
+    pub ip: String,
+}
+
+/// プロトコル番号と、それを使うJava Editionのリリース
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProtocolVersion {
+    /// リリース名 ("1.21.4", "1.21.2-1.21.3" など)
+    pub name: String,
+    /// プロトコル番号
+    pub protocol: i64,
+}
+
+/// 外部のページへのリンク
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Links {
+    /// Xのアカウント名 (@なし)
+    pub x: Option<String>,
+    /// WebサイトのURL
+    pub website: Option<String>,
+    /// Discordの招待URL
+    pub discord: Option<String>,
+}
+
+/// サーバーリストの1ページ
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServerListPage {
+    /// 指定したcursorのスナップショットが既に破棄されているか、cursorが不正か、最初のページと引数が異なる
+    /// スナップショットは公開から5分間保持する
+    /// このときserversは空なので、最初のページから取得し直す
+    pub cursor_expired: bool,
+    /// 続きを取得するためのカーソル
+    /// 続きがなければnull
+    pub next_cursor: Option<String>,
+    /// このページのサーバー
+    pub servers: Vec<Server>,
+    /// ページを切り出したスナップショットの番号
+    pub generation: i64,
+    /// 絞り込み後の全件数
+    pub total: i64,
+}
+
+/// Rankedの順序のスコア
+/// 各要素にバックエンドの設定の重みを掛けたもので、合計がtotalになる
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RankingScore {
+    /// 直近30日間の稼働率
+    pub uptime: f64,
+    /// servers.tomlで指定された補正値
+    pub boost: f64,
+    /// 合計
+    pub total: f64,
+    /// pingの応答の速さ
+    pub latency: f64,
+    /// 7日間の平均プレイヤー人数 (対数)
+    pub average_players: f64,
+    /// 現在のプレイヤー人数 (対数)
+    pub players: f64,
+}
+
+/// ある期間のプレイヤー人数の集計
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlayerStats {
+    /// 1時間ごとの平均人数の中央値
+    pub median: f64,
+    /// 平均プレイヤー人数
+    pub average: f64,
+    /// 最大プレイヤー人数
+    pub peak: i64,
+}
+
+/// サーバーの稼働状態
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Health {
+    /// 直前のpingに成功した
+
+Online,
+    /// pingに失敗し、オフラインかどうか再確認中
+
+Suspect,
+    /// 失敗が続いたためオフラインと判定した
+
+Offline,
+    /// pingには応答するがメンテナンス中 (is_onlineはfalse)
+
+Maintenance,
+}
+
+/// オフラインと判定された理由
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum OfflineReason {
+    /// 名前解決・接続・応答のいずれかに失敗した
+
+Unreachable,
+    /// 接続先が内部ネットワーク等のアドレスのため接続しなかった
+
+Blocked,
+}
+
+
+/// サーバーの詳細
+/// ネットワークでは、motd・latency_stats・probe・historyは最初の入口 (endpointsの先頭) のもの
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServerDetail {
+    /// 直近7日間の1日ごとのプレイヤー人数とオンライン状態の推移
+    pub history: Vec<HistoryPoint>,
+    /// 最後に成功したpingのMOTD
+    /// 一度も成功していなければnull
+    pub motd: Option<Motd>,
+    pub server: Server,
+    /// pingの状況
+    pub probe: ProbeDiagnostics,
+    /// 直近24時間のpingの往復時間
+    /// 記録がなければnull
+    pub latency_stats: Option<LatencyStats>,
+}
+
+/// pingで返されたMOTD
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Motd {
+    /// 書式を書式コード (§) で表したもの
+    pub legacy: String,
+    /// 書式を取り除いたもの
+    pub plain: String,
+    /// サーバーが返したJSONそのまま
+    pub json: String,
+}
+
+/// pingの往復時間の集計 (ミリ秒)
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LatencyStats {
+    pub max: i64,
+    /// 集計したpingの回数
+    pub samples: i64,
+    pub min: i64,
+    pub average: f64,
+}
+
+/// pingの状況
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProbeDiagnostics {
+    /// 最後に転送を受け付けるかを実際に確かめた時刻 (UNIX時間, 秒)
+    pub transfer_checked_at: Option<i64>,
+    /// 最後に転送を受け付けるかを実際に確かめた結果
+    /// 確かめていないか、判断できなければnull
+    pub accepts_transfers: Option<bool>,
+    /// 連続で失敗した回数
+    pub consecutive_failures: i64,
+    /// 最後に成功したpingの接続先 (IP:ポート)
+    pub resolved_address: Option<String>,
+    /// 次にpingする予定の時刻 (UNIX時間, 秒)
+    pub next_probe_at: i64,
+    /// 最後に失敗したpingのエラーメッセージ
+    /// 成功すると消える
+    pub last_error: Option<String>,
+    /// 最後にpingした時刻 (UNIX時間, 秒)
+    pub last_probe_at: Option<i64>,
+    /// 最後に成功したpingの接続にかかった時間 (ミリ秒)
+    pub connect_ms: Option<i64>,
+}
+
+/// 検索結果の1件
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchHit {
+    /// 関連度 (大きいほど関連が強い)
+    pub score: f64,
+    /// 一致したサーバー
+    pub server: Server,
+}
+
+/// プレイヤー人数の推移の1区間
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HistoryPoint {
+    /// 区間内の最大プレイヤー人数
+    pub players_peak: i64,
+    /// 区間の開始時刻 (UNIX時間, 秒)
+    pub timestamp: i64,
+    /// 区間内でオンラインだった割合 (0.0〜1.0)
+    pub online_ratio: f64,
+    /// 区間内で一度でもオンラインだったか
+    pub is_online: bool,
+    /// 区間内の平均プレイヤー人数
+    pub players_online: f64,
+}
+
+/// 履歴の区間の長さ
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub enum Resolution {
+    /// pingごと (直近7日間のみ)
+
+Raw,
+    /// 1時間ごと
+
+Hour,
+    /// 1日ごと (UTC)
+
+Day,
 }
 
 
@@ -43,8 +447,44 @@ use async_trait::async_trait;
 #[async_trait]
 pub trait API: Send + Sync + 'static {
     /// サーバーリストを取得する
-    /// 配列の順序はorderingに準拠する
+    /// 配列の順序はordering・directionに準拠する
+    /// directionを省略した場合はorderingごとの既定の向きになる
+    /// 残りの引数は絞り込みの条件で、指定したものをすべて満たすサーバーのみを返す
+    /// * online_only : trueならオンラインのサーバーのみ
+    /// * min_players・max_players : プレイヤー人数の範囲 (両端を含む)
+    /// * version : 対応バージョンの範囲にこのバージョン ("1.21.4" など) を含む (バージョンとして読み取れない場合はこの条件を無視する)
+    /// * protocol : プロトコル番号が一致する
+    /// * edition : このエディションで参加できる
+    /// * tags : すべてのタグを持つ
+    /// * software : サーバーソフトウェアが一致する (大文字小文字は区別しない)
+    /// * category : このジャンルを含む
+    /// * language : この言語に対応している ("ja" なら "ja-JP" も含む)
+    /// * audience : 想定する年齢層が一致する
+    /// limitを指定すると先頭からlimit件だけを返し、続きはnext_cursorをcursorに指定して取得する
+    /// cursorを指定したときは最初のページと同じスナップショットから返すので、途中で更新されても重複や抜けが起きない
+    /// cursorを指定するときはlimit以外の引数を最初のページと同じにすること (異なる場合はcursor_expiredになる)
+    /// localeを指定するとサーバーの名前・説明欄をその言語 ("en", "en-US" など) で返す (なければ既定の言語)
+    /// client_protocolにクライアントのプロトコル番号を指定すると、各サーバーに参加できるかをcompatibleで返す
     /// 定期的に更新するならキャッシュしても問題ない
-    async fn get_server_list(&self, ordering: Ordering) -> Vec<Server>;
+    async fn get_server_list(&self, ordering: Ordering, direction: Option<Direction>, online_only: Option<bool>, min_players: Option<i64>, max_players: Option<i64>, version: Option<String>, protocol: Option<i64>, edition: Option<Edition>, tags: Option<Vec<String>>, software: Option<String>, category: Option<Category>, language: Option<String>, audience: Option<Audience>, locale: Option<String>, client_protocol: Option<i64>, limit: Option<i64>, cursor: Option<String>) -> ServerListPage;
+    /// サーバーのプレイヤー人数とオンライン状態の推移を取得する
+    /// idはServerの値を指定する (ネットワークはget_serverと同じエントリーの推移になる)
+    /// from以上to未満の範囲を古い順に返す
+    /// 点が多すぎる場合は新しい方から5000個までを返す
+    /// 該当するサーバーがなければ空の配列を返す
+    async fn get_server_history(&self, id: String, from: i64, to: i64, resolution: Resolution) -> Vec<HistoryPoint>;
+    /// サーバーの名前・読み・説明欄を検索する
+    /// queryは空白で区切った語をすべて含むものに一致する
+    /// ひらがなとカタカナ、全角と半角、大文字と小文字は区別しない
+    /// 関連度の高い順に返し、同じ関連度ならorderingの既定の向きの順にする
+    /// limitを指定すると先頭からlimit件だけを返す
+    /// localeを指定するとサーバーの名前・説明欄をその言語 ("en", "en-US" など) で返す (なければ既定の言語)
+    async fn search_servers(&self, query: String, ordering: Ordering, limit: Option<i64>, locale: Option<String>) -> Vec<SearchHit>;
+    /// IDを指定してサーバーの詳細を取得する
+    /// localeを指定するとサーバーの名前・説明欄をその言語 ("en", "en-US" など) で返す (なければ既定の言語)
+    /// 該当するサーバーがなければnull
+    async fn get_server(&self, id: String, locale: Option<String>) -> Option<ServerDetail>;
+    /// プロトコル番号とリリース名の対応表を古い順に取得する
+    async fn get_protocol_versions(&self) -> Vec<ProtocolVersion>;
 }
 
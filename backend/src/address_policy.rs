@@ -0,0 +1,251 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+
+use crate::config::AddressPolicyConfig;
+
+/// 接続先アドレスの制限
+/// DNS/SRVの解決後に適用し、内部ネットワークへのpingを防ぐ
+#[derive(Debug, Clone)]
+pub struct AddressPolicy {
+    block_non_public: bool,
+    deny: Vec<IpNet>,
+    allow: Vec<IpNet>,
+}
+
+impl AddressPolicy {
+    pub fn new(config: &AddressPolicyConfig) -> Result<Self> {
+        Ok(Self {
+            block_non_public: config.block_non_public,
+            deny: parse_cidrs(&config.deny)?,
+            allow: parse_cidrs(&config.allow)?,
+        })
+    }
+
+    /// 接続してよいアドレスか
+    /// allowに含まれるものは常に許可する
+    /// 6to4・TeredoのアドレスはIPv4のアドレスを含むので、denyはそのIPv4のアドレスにも適用する
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        // IPv4射影アドレスはIPv4として扱う
+        let ip = match ip {
+            IpAddr::V6(v6) => v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(v6)),
+            ip => ip,
+        };
+
+        if self.allow.iter().any(|net| net.contains(&ip)) {
+            return true;
+        }
+        if self.block_non_public && is_non_public(ip) {
+            return false;
+        }
+        let embedded = match ip {
+            IpAddr::V6(v6) => embedded_ipv4(v6).map(IpAddr::V4),
+            IpAddr::V4(_) => None,
+        };
+        !self
+            .deny
+            .iter()
+            .any(|net| net.contains(&ip) || embedded.is_some_and(|v4| net.contains(&v4)))
+    }
+}
+
+impl Default for AddressPolicy {
+    fn default() -> Self {
+        Self::new(&AddressPolicyConfig::default()).expect("default cidrs must be valid")
+    }
+}
+
+fn parse_cidrs(cidrs: &[String]) -> Result<Vec<IpNet>> {
+    cidrs
+        .iter()
+        .map(|cidr| {
+            // 単一アドレスの指定も受け付ける
+            cidr.parse::<IpNet>()
+                .or_else(|_| cidr.parse::<IpAddr>().map(IpNet::from))
+                .with_context(|| format!("invalid CIDR: {cidr}"))
+        })
+        .collect()
+}
+
+/// 解決されたアドレスがすべてポリシーで拒否された
+#[derive(Debug)]
+pub struct BlockedAddressError {
+    pub host: String,
+    pub addrs: Vec<SocketAddr>,
+}
+
+impl fmt::Display for BlockedAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} resolves only to blocked addresses (", self.host)?;
+        for (i, addr) in self.addrs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{addr}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for BlockedAddressError {}
+
+/// ループバック・プライベート・リンクローカル等の外部から到達できないアドレスか
+pub fn is_non_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped().or_else(|| embedded_ipv4(v6)) {
+                return is_non_public(IpAddr::V4(v4));
+            }
+            let segments = v6.segments();
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || segments[..6] == [0; 6] // IPv4互換 ::/96 (廃止済み)
+                || (segments[0] & 0xfe00) == 0xfc00 // ユニークローカル fc00::/7
+                || (segments[0] & 0xffc0) == 0xfe80 // リンクローカル fe80::/10
+                || (segments[0] & 0xffc0) == 0xfec0 // サイトローカル fec0::/10 (廃止済み)
+        }
+    }
+}
+
+/// 6to4 (2002::/16) とTeredo (2001::/32) のアドレスが指すIPv4のアドレス
+/// Teredoはクライアントのアドレス (ビット反転して埋め込まれている) を返す
+fn embedded_ipv4(v6: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = v6.segments();
+    let [a, b] = match segments {
+        [0x2002, a, b, ..] => [a, b],
+        [0x2001, 0, .., a, b] => [!a, !b],
+        _ => return None,
+    };
+    Some(Ipv4Addr::from((a as u32) << 16 | b as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    fn policy(block_non_public: bool, deny: &[&str], allow: &[&str]) -> AddressPolicy {
+        AddressPolicy::new(&AddressPolicyConfig {
+            block_non_public,
+            deny: deny.iter().map(|cidr| cidr.to_string()).collect(),
+            allow: allow.iter().map(|cidr| cidr.to_string()).collect(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn non_public_ranges() {
+        for text in [
+            "10.0.0.1",
+            "127.0.0.1",
+            "169.254.0.1",
+            "192.168.1.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "224.0.0.1",
+            "::1",
+            "::",
+            "ff02::1",
+            "fd00::1",
+            "fe80::1",
+            "fec0::1",
+            // IPv4互換
+            "::a00:1",
+            "::8.8.8.8",
+            // IPv4射影
+            "::ffff:127.0.0.1",
+            // 6to4 (192.168.0.1)
+            "2002:c0a8:1::1",
+            // Teredo (クライアント 127.0.0.1)
+            "2001:0:4136:e378:8000:63bf:80ff:fffe",
+        ] {
+            assert!(is_non_public(ip(text)), "{text}");
+        }
+    }
+
+    #[test]
+    fn public_addresses() {
+        for text in [
+            "8.8.8.8",
+            "1.1.1.1",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+            // 6to4 (8.8.8.8)
+            "2002:808:808::1",
+            // Teredo (クライアント 8.8.8.8)
+            "2001:0:4136:e378:8000:63bf:f7f7:f7f7",
+        ] {
+            assert!(!is_non_public(ip(text)), "{text}");
+        }
+    }
+
+    #[test]
+    fn extracts_embedded_ipv4() {
+        let v6 = |text: &str| text.parse::<Ipv6Addr>().unwrap();
+        assert_eq!(
+            embedded_ipv4(v6("2002:c0a8:1::1")),
+            Some(Ipv4Addr::new(192, 168, 0, 1))
+        );
+        assert_eq!(
+            embedded_ipv4(v6("2001:0:4136:e378:8000:63bf:3fff:fdd2")),
+            Some(Ipv4Addr::new(192, 0, 2, 45))
+        );
+        assert_eq!(embedded_ipv4(v6("2001:db8::1")), None);
+        assert_eq!(embedded_ipv4(v6("2606:4700::1111")), None);
+    }
+
+    #[test]
+    fn allow_takes_precedence() {
+        let policy = policy(true, &["203.0.113.0/24"], &["127.0.0.1", "203.0.113.5"]);
+        assert!(policy.is_allowed(ip("127.0.0.1")));
+        assert!(policy.is_allowed(ip("::ffff:127.0.0.1")));
+        assert!(policy.is_allowed(ip("203.0.113.5")));
+        assert!(!policy.is_allowed(ip("127.0.0.2")));
+        assert!(!policy.is_allowed(ip("203.0.113.6")));
+        assert!(policy.is_allowed(ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn deny_applies_to_embedded_ipv4() {
+        let policy = policy(true, &["100.64.0.0/10"], &[]);
+        assert!(!policy.is_allowed(ip("100.64.0.1")));
+        assert!(!policy.is_allowed(ip("::ffff:100.64.0.1")));
+        assert!(!policy.is_allowed(ip("2002:6440:1::1")));
+        assert!(policy.is_allowed(ip("2002:808:808::1")));
+    }
+
+    #[test]
+    fn non_public_can_be_permitted() {
+        let policy = policy(false, &[], &[]);
+        assert!(policy.is_allowed(ip("192.168.1.1")));
+        assert!(policy.is_allowed(ip("fd00::1")));
+    }
+
+    #[test]
+    fn default_policy_denies_reserved_ranges() {
+        let policy = AddressPolicy::default();
+        assert!(!policy.is_allowed(ip("100.64.0.1")));
+        assert!(!policy.is_allowed(ip("2001:db8::1")));
+        assert!(!policy.is_allowed(ip("10.0.0.1")));
+        assert!(policy.is_allowed(ip("8.8.8.8")));
+    }
+}
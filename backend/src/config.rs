@@ -1,3 +1,5 @@
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -5,17 +7,508 @@ pub struct ServersConfig {
     pub servers: Vec<ServerConfig>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ServerConfig {
+    /// APIでサーバーを指定するためのID (英小文字・数字・ハイフン)
+    /// 省略した場合は [`ServerConfig::key`] を使う
+    pub id: Option<String>,
     pub ip: String,
     pub port: Option<u16>,
     pub icon: String,
-    pub name: String,
-    pub description: String,
+    pub name: LocalizedText,
+    /// 名前の読み (ひらがな・カタカナ)
+    /// 漢字を含む名前を名前順で正しく並べるために使う
+    pub reading: Option<String>,
+    pub description: LocalizedText,
+    /// Rankedの順序でのスコアの補正値 (-1.0〜1.0)
+    #[serde(default)]
+    pub boost: f64,
+    /// 絞り込み用のタグ (英小文字・数字・ハイフン)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 参加できるエディション
+    /// 統合版はGeyser等で参加できるサーバーのみ (pingはJava版のプロトコルで行う)
+    #[serde(default = "default_editions")]
+    pub editions: Vec<Edition>,
+    /// サーバーのジャンル
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    /// 対応している言語 ("ja", "en-US" など)
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// 想定する年齢層
+    pub audience: Option<Audience>,
+    /// 外部のページへのリンク
+    #[serde(default)]
+    pub links: Links,
+    /// 所属するネットワークのID (英小文字・数字・ハイフン)
+    /// 同じネットワークのエントリーはリスト上で1つにまとめられ、このIDがAPIでのIDになる
+    /// 表示に使う名前などはネットワークの最初のエントリーのもので、2つ目以降のnameは入口の名前になる
+    pub network: Option<String>,
+    /// 同じネットワークで同じ値を持つエントリーは、プレイヤーを共有している (同じプロキシの別の入口など)
+    /// 合計人数を数えるときに重複して数えない
+    pub player_pool: Option<String>,
+    /// 転送 (Transferパケット) による参加を受け付けるか (server.propertiesのaccepts-transfers)
+    /// 転送は1.20.5以降のサーバーでのみ使える
+    /// 省略した場合は不明として扱い、転送できないとはみなさない
+    pub accepts_transfers: Option<bool>,
+    /// サーバーリストに追加された日 (2024-05-01 など)
+    /// 省略した場合はバックエンドが初めてservers.tomlで見つけた時刻を使う
+    pub added_at: Option<toml::value::Datetime>,
+}
+
+/// 言語ごとに書き分けられる文字列
+/// 1つの文字列か、言語コードをキーにしたテーブルで書く
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum LocalizedText {
+    Plain(String),
+    Localized(BTreeMap<String, String>),
+}
+
+impl LocalizedText {
+    /// 指定がないときや、指定された言語がないときに使う言語
+    pub const DEFAULT_LOCALE: &str = "ja";
+
+    /// `locale` ("en", "en-US", "en_us" など) の文字列
+    /// 見つからなければ同じ言語のもの ("en" と "en-GB" など) を探し、それもなければ既定の言語、最初のものの順に使う
+    pub fn get(&self, locale: Option<&str>) -> &str {
+        let texts = match self {
+            Self::Plain(text) => return text,
+            Self::Localized(texts) => texts,
+        };
+        let find = |locale: &str| {
+            texts
+                .iter()
+                .find(|(key, _)| key.replace('_', "-").eq_ignore_ascii_case(locale))
+                .map(|(_, text)| text.as_str())
+        };
+        let locale = locale.map(|locale| locale.replace('_', "-"));
+        let find_language = |language: &str| {
+            texts
+                .iter()
+                .find(|(key, _)| {
+                    key.split(['-', '_'])
+                        .next()
+                        .is_some_and(|key| key.eq_ignore_ascii_case(language))
+                })
+                .map(|(_, text)| text.as_str())
+        };
+        let language = locale
+            .as_deref()
+            .map(|locale| locale.split('-').next().unwrap_or(locale));
+
+        locale
+            .as_deref()
+            .and_then(find)
+            .or_else(|| language.and_then(find))
+            .or_else(|| language.and_then(find_language))
+            .or_else(|| find(Self::DEFAULT_LOCALE))
+            .or_else(|| texts.values().next().map(String::as_str))
+            .unwrap_or("")
+    }
+
+    /// すべての言語の文字列
+    pub fn texts(&self) -> Vec<&str> {
+        match self {
+            Self::Plain(text) => vec![text],
+            Self::Localized(texts) => texts.values().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// サーバーのジャンル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Survival,
+    Minigames,
+    Pvp,
+    Rpg,
+    Creative,
+    Modded,
 }
 
-impl ServersConfig {
-    pub fn from_str(source: &str) -> Result<Self, toml::de::Error> {
+/// 想定する年齢層
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    /// 全年齢
+    Everyone,
+    /// 13歳以上
+    Teen,
+    /// 18歳以上
+    Adult,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Links {
+    /// WebサイトのURL
+    pub website: Option<String>,
+    /// Discordの招待URL
+    pub discord: Option<String>,
+    /// Xのアカウント名 (@なし)
+    pub x: Option<String>,
+}
+
+/// Minecraftのエディション
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edition {
+    Java,
+    Bedrock,
+}
+
+/// 1970-01-01からの日数 (グレゴリオ暦)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn default_editions() -> Vec<Edition> {
+    vec![Edition::Java]
+}
+
+impl ServerConfig {
+    /// エントリーの重複判定に使うキー
+    /// ホスト名の大文字小文字は区別しない
+    pub fn key(&self) -> String {
+        let host = self.ip.trim().to_lowercase();
+        match self.port {
+            Some(port) => format!("{host}:{port}"),
+            None => host,
+        }
+    }
+
+    /// APIでサーバーを指定するためのID
+    /// ホストやポートを変えない限り変わらない
+    pub fn id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| self.key())
+    }
+
+    /// [`ServerConfig::added_at`] のUNIX時間 (秒)
+    /// 時刻やオフセットを省略した場合はUTCの0時として扱い、日付がなければNone
+    pub fn added_at_unix(&self) -> Option<i64> {
+        let datetime = self.added_at.as_ref()?;
+        let date = datetime.date?;
+        let seconds = datetime.time.map_or(0, |time| {
+            time.hour as i64 * 3600 + time.minute as i64 * 60 + time.second as i64
+        });
+        let offset = match datetime.offset {
+            Some(toml::value::Offset::Custom { minutes }) => minutes as i64 * 60,
+            _ => 0,
+        };
+        let days = days_from_civil(date.year as i64, date.month as i64, date.day as i64);
+        Some(days * 86400 + seconds - offset)
+    }
+}
+
+impl FromStr for ServersConfig {
+    type Err = toml::de::Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
         toml::from_str(source)
     }
 }
+
+/// バックエンド自体の設定 (config.toml)
+/// 省略された項目はデフォルト値になる
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackendConfig {
+    pub registry: RegistryConfig,
+    pub address_policy: AddressPolicyConfig,
+    pub scheduler: SchedulerConfig,
+    pub health: HealthConfig,
+    pub history: HistoryConfig,
+    pub ranking: RankingConfig,
+    pub transfer: TransferConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegistryConfig {
+    /// servers.tomlの取得元
+    pub url: String,
+    /// 署名ファイルの取得元
+    /// 省略時は `url` に ".minisig" を付けたもの
+    pub signature_url: Option<String>,
+    /// 信頼するminisign公開鍵 (base64)
+    /// 空の場合は署名を検証しない
+    pub public_keys: Vec<String>,
+    /// servers.tomlを取得し直す間隔 (秒)
+    pub refresh_interval_secs: u64,
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            url: "https://raw.githubusercontent.com/Japan-Minecraft-Server-List/Minecraft-Servers-Registry/refs/heads/main/servers.toml".to_string(),
+            signature_url: None,
+            public_keys: Vec::new(),
+            refresh_interval_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AddressPolicyConfig {
+    /// プライベート・ループバック・リンクローカル・マルチキャスト等を拒否する
+    pub block_non_public: bool,
+    /// 追加で拒否するCIDR
+    pub deny: Vec<String>,
+    /// 常に許可するCIDR (テスト用)
+    pub allow: Vec<String>,
+}
+
+impl Default for AddressPolicyConfig {
+    fn default() -> Self {
+        Self {
+            block_non_public: true,
+            deny: [
+                "0.0.0.0/8",
+                "100.64.0.0/10",
+                "192.0.0.0/24",
+                "198.18.0.0/15",
+                "240.0.0.0/4",
+                "64:ff9b::/96",
+                "2001:db8::/32",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// 基本のping間隔 (秒)
+    pub base_interval_secs: u64,
+    /// 人気・変動の大きいサーバーのping間隔 (秒)
+    pub min_interval_secs: u64,
+    /// 失敗が続くサーバーのping間隔の上限 (秒)
+    pub max_backoff_secs: u64,
+    /// この人数以上のサーバーは短い間隔でpingする
+    pub popular_players: i32,
+    /// 前回からこの人数以上増減したサーバーは短い間隔でpingする
+    pub volatile_players_delta: i32,
+    /// 起動直後のpingの開始時刻を散らす範囲 (基本の間隔に対する割合 0.0〜1.0)
+    pub spread_ratio: f64,
+    /// 同時に実行するpingの最大数
+    pub max_concurrent_probes: usize,
+    /// 同一IPへの同時接続の最大数
+    pub max_connections_per_ip: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            base_interval_secs: 600,
+            min_interval_secs: 120,
+            max_backoff_secs: 3600,
+            popular_players: 50,
+            volatile_players_delta: 10,
+            spread_ratio: 0.8,
+            max_concurrent_probes: 256,
+            max_connections_per_ip: 4,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// オンラインだったサーバーをオフラインにするまでに再確認する回数
+    pub retries: u32,
+    /// 再確認のpingまでの間隔 (秒)
+    pub retry_delay_secs: u64,
+    /// メンテナンス中とみなすバージョン名に含まれる文字列
+    /// 大文字小文字・全角半角・ひらがなとカタカナは区別しない
+    pub maintenance_versions: Vec<String>,
+    /// メンテナンス中とみなすMOTDに含まれる文字列 (区別しないものはバージョン名と同じ)
+    pub maintenance_motds: Vec<String>,
+    /// メンテナンス中とみなすプロトコル番号
+    /// メンテナンス用のプラグインは-1を返すことが多い
+    pub maintenance_protocols: Vec<i32>,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            retry_delay_secs: 15,
+            maintenance_versions: ["maintenance", "メンテナンス", "メンテ中"]
+                .map(String::from)
+                .to_vec(),
+            maintenance_motds: ["under maintenance", "メンテナンス中", "メンテ中"]
+                .map(String::from)
+                .to_vec(),
+            maintenance_protocols: vec![-1],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// SQLiteのデータベースファイル
+    pub path: String,
+    /// pingごとの生データを残す日数
+    pub raw_retention_days: u32,
+    /// 1時間ごとの集計を残す日数
+    pub hourly_retention_days: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            path: "history.sqlite3".to_string(),
+            raw_retention_days: 7,
+            hourly_retention_days: 365,
+        }
+    }
+}
+
+/// 並び順の設定
+/// 重みはRankedの順序のスコアのもので、各要素はおおよそ0.0〜1.0 (人数はその対数) に揃えてから掛ける
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RankingConfig {
+    /// 現在のプレイヤー人数
+    pub players: f64,
+    /// 7日間の平均プレイヤー人数
+    pub average_players: f64,
+    /// 直近30日間の稼働率
+    pub uptime: f64,
+    /// pingの応答の速さ
+    pub latency: f64,
+    /// servers.tomlで指定された補正値
+    pub boost: f64,
+    /// 同順位のサーバーの並びとShuffleの順序を入れ替える間隔 (秒)
+    pub rotation_window_secs: u64,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            players: 1.0,
+            average_players: 1.0,
+            uptime: 2.0,
+            latency: 0.5,
+            boost: 1.0,
+            rotation_window_secs: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TransferConfig {
+    /// 転送による参加を受け付けるかを、実際にログインを始めて確かめる
+    /// 確かめた結果はservers.tomlのaccepts_transfersより優先する
+    pub check: bool,
+    /// 確かめ直す間隔 (秒)
+    pub check_interval_secs: u64,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            check: false,
+            check_interval_secs: 6 * 60 * 60,
+        }
+    }
+}
+
+impl BackendConfig {
+    /// 設定ファイルを読み込む
+    /// ファイルが存在しなければデフォルト値を使う
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let source = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&source)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::server_config;
+
+    fn localized(texts: &[(&str, &str)]) -> LocalizedText {
+        LocalizedText::Localized(
+            texts
+                .iter()
+                .map(|(locale, text)| (locale.to_string(), text.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn id_defaults_to_the_address() {
+        let config = server_config("ip = \"Play.Example.com\"\nport = 25566");
+        assert_eq!(config.key(), "play.example.com:25566");
+        assert_eq!(config.id(), "play.example.com:25566");
+
+        // ポートを省略したものは別のアドレスとして扱う
+        let config = server_config("ip = \"play.example.com\"");
+        assert_eq!(config.id(), "play.example.com");
+
+        let config = server_config("ip = \"play.example.com\"\nid = \"play\"");
+        assert_eq!(config.key(), "play.example.com");
+        assert_eq!(config.id(), "play");
+    }
+
+    #[test]
+    fn plain_text_ignores_locale() {
+        let text = LocalizedText::Plain("サバイバル".to_string());
+        assert_eq!(text.get(None), "サバイバル");
+        assert_eq!(text.get(Some("en")), "サバイバル");
+    }
+
+    #[test]
+    fn finds_the_exact_locale() {
+        let text = localized(&[("ja", "日本語"), ("en", "English"), ("en-GB", "British")]);
+        assert_eq!(text.get(Some("en")), "English");
+        assert_eq!(text.get(Some("en-GB")), "British");
+        assert_eq!(text.get(Some("en_gb")), "British");
+        assert_eq!(text.get(Some("EN-gb")), "British");
+    }
+
+    #[test]
+    fn falls_back_to_the_same_language() {
+        let text = localized(&[("ja", "日本語"), ("en", "English")]);
+        assert_eq!(text.get(Some("en-US")), "English");
+
+        let text = localized(&[("ja", "日本語"), ("en-GB", "British")]);
+        assert_eq!(text.get(Some("en")), "British");
+        assert_eq!(text.get(Some("en-US")), "British");
+
+        let text = localized(&[("ja", "日本語"), ("en_US", "American")]);
+        assert_eq!(text.get(Some("en-us")), "American");
+    }
+
+    #[test]
+    fn falls_back_to_the_default_locale() {
+        let text = localized(&[("en", "English"), ("ja", "日本語")]);
+        assert_eq!(text.get(None), "日本語");
+        assert_eq!(text.get(Some("ko")), "日本語");
+    }
+
+    #[test]
+    fn falls_back_to_the_first_text() {
+        let text = localized(&[("ko", "한국어"), ("en", "English")]);
+        assert_eq!(text.get(Some("zh")), "English");
+        assert_eq!(localized(&[]).get(Some("ja")), "");
+    }
+}
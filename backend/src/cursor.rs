@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+
+/// サーバーリストの続きを取得するためのカーソル
+/// どのスナップショットの何件目から続けるかと、最初のページの条件を持つ
+/// APIでは中身を解釈しない文字列として扱ってもらう
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// ページを切り出したスナップショットの番号
+    pub generation: u64,
+    /// 最初のページの並び順・絞り込みなどの条件 ([`Cursor::query_hash`])
+    /// 条件を変えて続きを取得しようとした場合は使えない
+    pub query: u64,
+    /// 次のページの先頭の位置 (絞り込み後の件数で数える)
+    pub offset: usize,
+}
+
+impl Cursor {
+    /// ページの条件 (limitとcursor以外の引数) をまとめた値
+    /// FNV-1aでDebug表現をハッシュする
+    pub fn query_hash(query: &impl Debug) -> u64 {
+        format!("{:?}", query)
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// APIで返す文字列にする
+    pub fn encode(&self) -> String {
+        format!(
+            "{:016x}{:016x}{:08x}",
+            self.generation, self.query, self.offset
+        )
+    }
+
+    /// [`Cursor::encode`] で作った文字列を読み取る
+    /// 形式が不正ならNone
+    pub fn decode(text: &str) -> Option<Self> {
+        if text.len() != 40 || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(Self {
+            generation: u64::from_str_radix(&text[..16], 16).ok()?,
+            query: u64::from_str_radix(&text[16..32], 16).ok()?,
+            offset: usize::from_str_radix(&text[32..], 16).ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        for cursor in [
+            Cursor {
+                generation: 0,
+                query: 0,
+                offset: 0,
+            },
+            Cursor {
+                generation: 42,
+                query: Cursor::query_hash(&("Player", Some("ja"))),
+                offset: 100,
+            },
+            Cursor {
+                generation: u64::MAX,
+                query: u64::MAX,
+                offset: u32::MAX as usize,
+            },
+        ] {
+            let text = cursor.encode();
+            assert_eq!(text.len(), 40);
+            assert_eq!(Cursor::decode(&text), Some(cursor));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_text() {
+        assert_eq!(Cursor::decode(""), None);
+        assert_eq!(Cursor::decode("000000000000002a00000064"), None);
+        assert_eq!(
+            Cursor::decode("000000000000002a000000000000000000000064a"),
+            None
+        );
+        assert_eq!(
+            Cursor::decode("000000000000002a00000000000000000000006g"),
+            None
+        );
+        assert_eq!(
+            Cursor::decode("+00000000000002a000000000000000000000064"),
+            None
+        );
+        assert_eq!(Cursor::decode("００００"), None);
+    }
+
+    #[test]
+    fn query_hash_depends_on_every_argument() {
+        let base = Cursor::query_hash(&("Player", None::<&str>, Some("ja")));
+        assert_eq!(
+            base,
+            Cursor::query_hash(&("Player", None::<&str>, Some("ja")))
+        );
+        assert_ne!(
+            base,
+            Cursor::query_hash(&("Player", Some("Ascending"), Some("ja")))
+        );
+        assert_ne!(
+            base,
+            Cursor::query_hash(&("Player", None::<&str>, Some("en")))
+        );
+        assert_ne!(
+            base,
+            Cursor::query_hash(&("Name", None::<&str>, Some("ja")))
+        );
+    }
+}
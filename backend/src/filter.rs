@@ -0,0 +1,245 @@
+use crate::{
+    config::{Audience, Category, Edition},
+    service::MinecraftServerStatus,
+    version::{GameVersion, supported_range},
+};
+
+/// サーバーリストの絞り込み条件
+/// 指定されていない条件は常に満たすものとする
+#[derive(Debug, Default)]
+pub struct ServerFilter {
+    /// オンラインのサーバーのみ
+    pub online_only: bool,
+    pub min_players: Option<i32>,
+    pub max_players: Option<i32>,
+    /// バージョン名から読み取れる対応範囲にこのバージョンを含む
+    pub version: Option<GameVersion>,
+    /// pingで返されたプロトコル番号が一致する
+    pub protocol: Option<i32>,
+    pub edition: Option<Edition>,
+    /// すべてのタグを持つ
+    pub tags: Vec<String>,
+    /// サーバーソフトウェア (大文字小文字は区別しない)
+    pub software: Option<String>,
+    pub category: Option<Category>,
+    /// 対応言語 ("ja" なら "ja-JP" も含む)
+    pub language: Option<String>,
+    pub audience: Option<Audience>,
+}
+
+impl ServerFilter {
+    pub fn matches(&self, status: &MinecraftServerStatus) -> bool {
+        if self.online_only && !status.is_online {
+            return false;
+        }
+        if self
+            .min_players
+            .is_some_and(|min| status.players_online < min)
+            || self
+                .max_players
+                .is_some_and(|max| status.players_online > max)
+        {
+            return false;
+        }
+
+        // バージョン・プロトコル・ソフトウェアはpingの結果から判断するので、オフラインなら満たさない
+        if let Some(version) = self.version {
+            let in_range = status.is_online
+                && supported_range(&status.version_name)
+                    .is_some_and(|(min, max)| (min..=max).contains(&version));
+            if !in_range {
+                return false;
+            }
+        }
+        if let Some(protocol) = self.protocol
+            && !(status.is_online && status.version_protocol == protocol)
+        {
+            return false;
+        }
+        if let Some(software) = &self.software {
+            let matches = status
+                .software
+                .is_some_and(|detected| detected.eq_ignore_ascii_case(software));
+            if !matches {
+                return false;
+            }
+        }
+
+        if let Some(edition) = self.edition
+            && !status.editions.contains(&edition)
+        {
+            return false;
+        }
+        if let Some(category) = self.category
+            && !status.categories.contains(&category)
+        {
+            return false;
+        }
+        if let Some(language) = &self.language {
+            let matches = status.languages.iter().any(|supported| {
+                supported.eq_ignore_ascii_case(language)
+                    || supported
+                        .split_once('-')
+                        .is_some_and(|(code, _)| code.eq_ignore_ascii_case(language))
+            });
+            if !matches {
+                return false;
+            }
+        }
+        if self.audience.is_some() && status.audience != self.audience {
+            return false;
+        }
+
+        self.tags.iter().all(|tag| {
+            status
+                .tags
+                .iter()
+                .any(|status_tag| status_tag.eq_ignore_ascii_case(tag))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{offline_status, online_status, server_config},
+        version::parse_version,
+    };
+
+    /// Paper 1.21.4 で `players_online` 人のサーバー
+    fn online(players_online: i32) -> MinecraftServerStatus {
+        online_status(
+            &server_config("ip = \"a\"\ntags = [\"Economy\", \"beginner\"]"),
+            players_online,
+        )
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = ServerFilter::default();
+        assert!(filter.matches(&online(10)));
+        assert!(filter.matches(&offline_status(&server_config("ip = \"a\""))));
+    }
+
+    #[test]
+    fn online_only_and_player_range() {
+        let offline = offline_status(&server_config("ip = \"a\""));
+        let online_only = ServerFilter {
+            online_only: true,
+            ..Default::default()
+        };
+        assert!(online_only.matches(&online(0)));
+        assert!(!online_only.matches(&offline));
+
+        let range = ServerFilter {
+            min_players: Some(5),
+            max_players: Some(10),
+            ..Default::default()
+        };
+        assert!(!range.matches(&online(4)));
+        assert!(range.matches(&online(5)));
+        assert!(range.matches(&online(10)));
+        assert!(!range.matches(&online(11)));
+    }
+
+    #[test]
+    fn version_protocol_and_software_need_a_response() {
+        let offline = offline_status(&server_config("ip = \"a\""));
+        let filters = [
+            ServerFilter {
+                version: parse_version("1.21.4"),
+                ..Default::default()
+            },
+            ServerFilter {
+                protocol: Some(769),
+                ..Default::default()
+            },
+            ServerFilter {
+                software: Some("paper".to_string()),
+                ..Default::default()
+            },
+        ];
+        for filter in filters {
+            assert!(filter.matches(&online(0)), "{filter:?}");
+            assert!(!filter.matches(&offline), "{filter:?}");
+        }
+
+        let other_version = ServerFilter {
+            version: parse_version("1.20.1"),
+            ..Default::default()
+        };
+        assert!(!other_version.matches(&online(0)));
+        let other_software = ServerFilter {
+            software: Some("Spigot".to_string()),
+            ..Default::default()
+        };
+        assert!(!other_software.matches(&online(0)));
+    }
+
+    #[test]
+    fn edition_and_tags() {
+        let bedrock = ServerFilter {
+            edition: Some(Edition::Bedrock),
+            ..Default::default()
+        };
+        assert!(!bedrock.matches(&online(0)));
+        let cross_play = online_status(
+            &server_config("ip = \"a\"\neditions = [\"java\", \"bedrock\"]"),
+            0,
+        );
+        assert!(bedrock.matches(&cross_play));
+
+        // すべてのタグを大文字小文字を区別せずに持つものだけ
+        let tags = |tags: &[&str]| ServerFilter {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(tags(&["economy"]).matches(&online(0)));
+        assert!(tags(&["economy", "Beginner"]).matches(&online(0)));
+        assert!(!tags(&["economy", "pvp"]).matches(&online(0)));
+    }
+
+    #[test]
+    fn category_language_and_audience() {
+        let config = server_config(
+            "ip = \"a\"
+categories = [\"survival\", \"rpg\"]
+languages = [\"ja-JP\", \"en\"]
+audience = \"teen\"",
+        );
+        let status = online_status(&config, 0);
+        let unlabeled = online_status(&server_config("ip = \"b\""), 0);
+
+        let rpg = ServerFilter {
+            category: Some(Category::Rpg),
+            ..Default::default()
+        };
+        assert!(rpg.matches(&status));
+        assert!(!rpg.matches(&unlabeled));
+        let pvp = ServerFilter {
+            category: Some(Category::Pvp),
+            ..Default::default()
+        };
+        assert!(!pvp.matches(&status));
+
+        // 地域付きの言語は言語だけの指定にも一致する
+        let language = |language: &str| ServerFilter {
+            language: Some(language.to_string()),
+            ..Default::default()
+        };
+        assert!(language("ja").matches(&status));
+        assert!(language("JA-jp").matches(&status));
+        assert!(language("en").matches(&status));
+        assert!(!language("en-US").matches(&status));
+        assert!(!language("ja").matches(&unlabeled));
+
+        let audience = |audience| ServerFilter {
+            audience: Some(audience),
+            ..Default::default()
+        };
+        assert!(audience(Audience::Teen).matches(&status));
+        assert!(!audience(Audience::Everyone).matches(&status));
+        assert!(!audience(Audience::Teen).matches(&unlabeled));
+    }
+}
@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use crate::{
+    config::HealthConfig,
+    minecraft::MinecraftServerInfo,
+    service::OfflineReason,
+    text::{normalize_for_search, strip_formatting},
+};
+
+/// サーバーの稼働状態
+/// 一時的なパケットロスでリストから消えないよう、失敗が続いたときだけオフラインにする
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// 直前のpingに成功した
+    Online,
+    /// オンラインだったがpingに失敗し、再確認中
+    /// 表示上はオンラインのまま扱う
+    Suspect,
+    /// 失敗が続いたためオフラインと判定した
+    Offline,
+    /// pingには応答するが、バージョン名・MOTD・プロトコル番号からメンテナンス中と判定した
+    /// 参加できないのでオンラインとしては扱わない
+    Maintenance,
+}
+
+impl Health {
+    /// リスト上でオンラインとして扱うか
+    pub fn is_online(self) -> bool {
+        matches!(self, Self::Online | Self::Suspect)
+    }
+}
+
+/// オフライン・メンテナンス判定の基準
+pub struct HealthPolicy {
+    retries: u32,
+    retry_delay: Duration,
+    /// 正規化済み ([`normalize_for_search`])
+    maintenance_versions: Vec<String>,
+    /// 正規化済み ([`normalize_for_search`])
+    maintenance_motds: Vec<String>,
+    maintenance_protocols: Vec<i32>,
+}
+
+impl HealthPolicy {
+    pub fn new(config: &HealthConfig) -> Self {
+        Self {
+            retries: config.retries,
+            retry_delay: Duration::from_secs(config.retry_delay_secs.max(1)),
+            maintenance_versions: normalize_patterns(&config.maintenance_versions),
+            maintenance_motds: normalize_patterns(&config.maintenance_motds),
+            maintenance_protocols: config.maintenance_protocols.clone(),
+        }
+    }
+
+    /// pingに成功したときの状態
+    /// メンテナンス中の表示があればMaintenance
+    pub fn on_success(&self, info: &MinecraftServerInfo) -> Health {
+        let matches = |text: &str, patterns: &[String]| {
+            let text = normalize_for_search(&strip_formatting(text));
+            patterns
+                .iter()
+                .any(|pattern| text.contains(pattern.as_str()))
+        };
+        if self.maintenance_protocols.contains(&info.version_protocol)
+            || matches(&info.version_name, &self.maintenance_versions)
+            || matches(&info.motd, &self.maintenance_motds)
+        {
+            Health::Maintenance
+        } else {
+            Health::Online
+        }
+    }
+
+    /// 再確認のpingまでの間隔
+    pub fn retry_delay(&self) -> Duration {
+        self.retry_delay
+    }
+
+    /// pingに失敗したときの次の状態
+    /// `consecutive_failures` は今回の失敗を含めた連続失敗回数
+    pub fn on_failure(
+        &self,
+        current: Option<Health>,
+        consecutive_failures: u32,
+        reason: OfflineReason,
+    ) -> Health {
+        match (current, reason) {
+            // アドレスポリシーでの拒否は再試行しても変わらない
+            (_, OfflineReason::Blocked) => Health::Offline,
+            (Some(Health::Online | Health::Suspect), _) if consecutive_failures <= self.retries => {
+                Health::Suspect
+            }
+            // メンテナンス中も一時的な失敗ではオフラインにしない
+            (Some(Health::Maintenance), _) if consecutive_failures <= self.retries => {
+                Health::Maintenance
+            }
+            _ => Health::Offline,
+        }
+    }
+
+    /// オフラインになってからの連続失敗回数 (バックオフの計算用)
+    pub fn failures_since_offline(&self, consecutive_failures: u32) -> u32 {
+        consecutive_failures.saturating_sub(self.retries).max(1)
+    }
+}
+
+/// 判定用の文字列を正規化する (空のものは捨てる)
+fn normalize_patterns(patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .map(|pattern| normalize_for_search(pattern))
+        .filter(|pattern| !pattern.trim().is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::server_info;
+
+    fn policy() -> HealthPolicy {
+        HealthPolicy::new(&HealthConfig::default())
+    }
+
+    #[test]
+    fn normal_response_is_online() {
+        let policy = policy();
+        assert_eq!(
+            policy.on_success(&server_info("Paper 1.21.4", 769, "Welcome!")),
+            Health::Online
+        );
+    }
+
+    #[test]
+    fn detects_maintenance_by_protocol_sentinel() {
+        let policy = policy();
+        assert_eq!(
+            policy.on_success(&server_info("Paper 1.21.4", -1, "Welcome!")),
+            Health::Maintenance
+        );
+        assert!(!Health::Maintenance.is_online());
+    }
+
+    #[test]
+    fn detects_maintenance_by_normalized_patterns() {
+        let policy = policy();
+        for (version_name, motd) in [
+            ("Maintenance", "Welcome!"),
+            ("§cＭＡＩＮＴＥＮＡＮＣＥ", "Welcome!"),
+            ("ﾒﾝﾃﾅﾝｽ", "Welcome!"),
+            ("Paper 1.21.4", "Server is §lUnder Maintenance§r"),
+            ("Paper 1.21.4", "ただいまめんて中です"),
+        ] {
+            assert_eq!(
+                policy.on_success(&server_info(version_name, 769, motd)),
+                Health::Maintenance,
+                "{version_name} / {motd}"
+            );
+        }
+    }
+
+    #[test]
+    fn patterns_are_configurable() {
+        let policy = HealthPolicy::new(&HealthConfig {
+            maintenance_versions: vec!["Closed".to_string(), " ".to_string()],
+            maintenance_motds: Vec::new(),
+            maintenance_protocols: Vec::new(),
+            ..HealthConfig::default()
+        });
+        assert_eq!(
+            policy.on_success(&server_info("closed", 769, "")),
+            Health::Maintenance
+        );
+        // 空のパターンはすべてに一致してしまうので捨てる
+        assert_eq!(
+            policy.on_success(&server_info("Paper 1.21.4", -1, "under maintenance")),
+            Health::Online
+        );
+    }
+
+    /// 既定の設定で再確認するのは2回まで
+    #[test]
+    fn online_becomes_suspect_then_offline() {
+        let policy = policy();
+        let mut health = Some(Health::Online);
+        let mut transitions = Vec::new();
+        for failures in 1..=4 {
+            let next = policy.on_failure(health, failures, OfflineReason::Unreachable);
+            transitions.push(next);
+            health = Some(next);
+        }
+        assert_eq!(
+            transitions,
+            vec![
+                Health::Suspect,
+                Health::Suspect,
+                Health::Offline,
+                Health::Offline
+            ]
+        );
+        assert!(Health::Suspect.is_online());
+        assert!(!Health::Offline.is_online());
+    }
+
+    #[test]
+    fn never_probed_or_offline_stays_offline() {
+        let policy = policy();
+        assert_eq!(
+            policy.on_failure(None, 1, OfflineReason::Unreachable),
+            Health::Offline
+        );
+        assert_eq!(
+            policy.on_failure(Some(Health::Offline), 1, OfflineReason::Unreachable),
+            Health::Offline
+        );
+    }
+
+    #[test]
+    fn blocked_goes_straight_to_offline() {
+        let policy = policy();
+        for current in [
+            None,
+            Some(Health::Online),
+            Some(Health::Suspect),
+            Some(Health::Maintenance),
+        ] {
+            assert_eq!(
+                policy.on_failure(current, 1, OfflineReason::Blocked),
+                Health::Offline
+            );
+        }
+    }
+
+    #[test]
+    fn maintenance_is_kept_during_the_retry_window() {
+        let policy = policy();
+        let current = Some(Health::Maintenance);
+        assert_eq!(
+            policy.on_failure(current, 1, OfflineReason::Unreachable),
+            Health::Maintenance
+        );
+        assert_eq!(
+            policy.on_failure(current, 2, OfflineReason::Unreachable),
+            Health::Maintenance
+        );
+        assert_eq!(
+            policy.on_failure(current, 3, OfflineReason::Unreachable),
+            Health::Offline
+        );
+    }
+
+    #[test]
+    fn backoff_counts_failures_after_going_offline() {
+        let policy = policy();
+        assert_eq!(policy.failures_since_offline(1), 1);
+        assert_eq!(policy.failures_since_offline(3), 1);
+        assert_eq!(policy.failures_since_offline(5), 3);
+    }
+}
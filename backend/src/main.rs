@@ -1,23 +1,61 @@
-use std::sync::Arc;
+use std::{process::ExitCode, sync::Arc};
 
 use tokio::spawn;
-use tracing::info;
+use tracing::{error, info};
 
-use crate::{log::setup_tracing, server::ApiServer, service::Service};
+use crate::{config::BackendConfig, log::setup_tracing, server::ApiServer, service::Service};
 
+pub mod address_policy;
 pub mod config;
+pub mod cursor;
+pub mod filter;
+pub mod health;
 pub mod log;
 pub mod minecraft;
+pub mod network;
+pub mod ordering;
+pub mod ranking;
+pub mod registry;
+pub mod scheduler;
+pub mod search;
 pub mod server;
 pub mod service;
+pub mod stats;
+pub mod storage;
+#[cfg(test)]
+pub mod testing;
+pub mod text;
+pub mod validate;
+pub mod version;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
+    // サブコマンド
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("validate") {
+        return validate::run_cli(args.get(2).map(String::as_str));
+    }
+
     // ログのセットアップ
     let _log_guard = setup_tracing();
 
+    // 設定ファイル
+    let config = match BackendConfig::load("config.toml") {
+        Ok(config) => config,
+        Err(error) => {
+            error!("Failed to load config.toml : {:#}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
     // Minecraftサーバーのステータスチェッカー
-    let service = Arc::new(Service::new());
+    let service = match Service::new(&config) {
+        Ok(service) => Arc::new(service),
+        Err(error) => {
+            error!("Failed to start service : {:#}", error);
+            return ExitCode::FAILURE;
+        }
+    };
 
     let service_ = service.clone();
 
@@ -33,4 +71,6 @@ async fn main() {
 
     // APIサーバーを起動する
     server.serve().await;
+
+    ExitCode::SUCCESS
 }
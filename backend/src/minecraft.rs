@@ -6,12 +6,16 @@ use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::sync::OwnedSemaphorePermit;
 use tokio::task::JoinSet;
 use tokio::time::timeout;
 
 use hickory_resolver::TokioAsyncResolver;
 use hickory_resolver::proto::rr::rdata::SRV;
 
+use crate::address_policy::{AddressPolicy, BlockedAddressError};
+use crate::scheduler::IpLimiter;
+
 #[derive(Debug, Clone)]
 pub struct MinecraftServerInfo {
     pub host: String,
@@ -24,34 +28,82 @@ pub struct MinecraftServerInfo {
     pub players_online: i32,
     pub players_max: i32,
     pub motd: String,
+    pub motd_legacy: String, // 書式を書式コード (§) で表したもの
+    pub motd_json: String,   // サーバーが返したJSONそのまま
+}
+
+/// 1回のpingで同時に接続を試みるアドレスの最大数
+const MAX_RACE_CANDIDATES: usize = 4;
+
+/// Status ResponseのJSONの最大バイト数（faviconを含むので大きめ）
+const MAX_STATUS_JSON_BYTES: usize = 1024 * 1024;
+
+/// ログイン中のDisconnectの理由 (JSON) の最大バイト数
+const MAX_DISCONNECT_REASON_BYTES: usize = 32 * 1024;
+
+/// 全pingで共有する資源
+/// リゾルバ（キャッシュを効かせるため）、アドレスポリシー、接続先IPごとの同時接続数制限
+pub struct Prober {
+    resolver: TokioAsyncResolver,
+    policy: AddressPolicy,
+    ip_limiter: IpLimiter,
+    force_v4: bool,
+}
+
+impl Prober {
+    pub fn new(policy: AddressPolicy, ip_limiter: IpLimiter) -> Result<Self> {
+        Ok(Self {
+            resolver: TokioAsyncResolver::tokio_from_system_conf().context("init resolver")?,
+            policy,
+            ip_limiter,
+            force_v4: std::env::var("MC_FORCE_IPV4").ok().as_deref() == Some("1"),
+        })
+    }
 }
 
 impl MinecraftServerInfo {
     /// Handshake → Status → Ping を実行
     /// `port`: Some(..) なら SRV をスキップ、None なら SRV を試す（失敗時は 25565）。
-    pub async fn query(host: &str, port: Option<u16>) -> Result<Self> {
-        let force_v4 = std::env::var("MC_FORCE_IPV4").ok().as_deref() == Some("1");
-        let resolver = TokioAsyncResolver::tokio_from_system_conf().context("init resolver")?;
+    /// 解決後のアドレスがすべてポリシーで拒否された場合は [`BlockedAddressError`]。
+    pub async fn query(host: &str, port: Option<u16>, prober: &Prober) -> Result<Self> {
+        let policy = &prober.policy;
 
         // 1) SRV を考慮した接続候補（SocketAddr）を構築
-        let candidates = resolve_minecraft_candidates(&resolver, host, port, force_v4)
-            .await
-            .with_context(|| format!("DNS/SRV resolution failed for {host}"))?;
+        let candidates =
+            resolve_minecraft_candidates(&prober.resolver, host, port, prober.force_v4)
+                .await
+                .with_context(|| format!("DNS/SRV resolution failed for {host}"))?;
         if candidates.is_empty() {
             anyhow::bail!("no candidates to connect");
         }
 
+        // 解決後のアドレスをポリシーで絞り込む（内部ネットワークには接続しない）
+        let (mut candidates, blocked): (Vec<_>, Vec<_>) = candidates
+            .into_iter()
+            .partition(|addr| policy.is_allowed(addr.ip()));
+        if candidates.is_empty() {
+            return Err(BlockedAddressError {
+                host: host.to_string(),
+                addrs: blocked,
+            }
+            .into());
+        }
+
+        candidates.truncate(MAX_RACE_CANDIDATES);
+
         // 2) 候補すべてに同時接続（最初に成功したものを採用）
+        // 接続先IPごとの枠は status のやり取りが終わるまで保持する
         let per_attempt = Duration::from_secs(3);
         let connect_start = Instant::now();
-        let (mut stream, chosen_addr) = connect_race_all(&candidates, per_attempt)
-            .await
-            .with_context(|| {
-                format!(
-                    "TCP connect failed (tried concurrently: {})",
-                    join_addrs(&candidates)
-                )
-            })?;
+        let (mut stream, chosen_addr, _ip_permit) =
+            connect_race_all(&candidates, per_attempt, &prober.ip_limiter)
+                .await
+                .with_context(|| {
+                    format!(
+                        "TCP connect failed (tried concurrently: {})",
+                        join_addrs(&candidates)
+                    )
+                })?;
         let connect_ms = connect_start.elapsed().as_millis();
 
         // 以降の I/O のソフトタイムアウト
@@ -63,16 +115,7 @@ impl MinecraftServerInfo {
         let protocol_version = 47; // status では任意。互換性重視
         let handshake_port = chosen_addr.port();
 
-        let mut payload = Vec::new();
-        payload.push(0x00); // packet id
-        write_varint(protocol_version, &mut payload);
-        write_mc_string(host, &mut payload);
-        payload.extend_from_slice(&handshake_port.to_be_bytes());
-        write_varint(1, &mut payload); // next state = status
-
-        let mut packet = Vec::new();
-        write_varint(payload.len() as i32, &mut packet);
-        packet.extend_from_slice(&payload);
+        let packet = handshake_packet(host, handshake_port, protocol_version, 1); // next state = status
         timeout(op_timeout, stream.write_all(&packet)).await??;
 
         // 4) Status Request
@@ -84,8 +127,11 @@ impl MinecraftServerInfo {
         if pid != 0x00 {
             anyhow::bail!("Unexpected packet id (expected 0x00), got {pid}");
         }
-        let json_len = timeout(op_timeout, read_varint(&mut stream)).await?? as usize;
-        let json_bytes = timeout(op_timeout, read_exact_n(&mut stream, json_len)).await??;
+        let json_bytes = timeout(
+            op_timeout,
+            read_mc_string_bytes(&mut stream, MAX_STATUS_JSON_BYTES),
+        )
+        .await??;
         let json_text = String::from_utf8(json_bytes)?;
         let status: StatusResponse = serde_json::from_str(&json_text)?;
 
@@ -114,6 +160,8 @@ impl MinecraftServerInfo {
 
         // 7) 整形
         let motd = description_to_text(&status.description);
+        let motd_legacy = description_to_legacy(&status.description);
+        let motd_json = description_to_json(&status.description);
         Ok(Self {
             host: host.to_string(),
             port_effective: handshake_port,
@@ -125,8 +173,78 @@ impl MinecraftServerInfo {
             players_online: status.players.online,
             players_max: status.players.max,
             motd,
+            motd_legacy,
+            motd_json,
         })
     }
+
+    /// 転送 (Transferパケット) による参加をサーバーが受け付けるかを確かめる
+    /// Handshake（next state = 3: transfer）→ Login Start を送り、最初の応答で判断する
+    /// ログインは完了させず、応答を受け取った時点で切断する
+    /// 転送以外の理由（ホワイトリスト等）で切断された場合は判断できないので None
+    /// `protocol`: ハンドシェイクで名乗るプロトコル番号（サーバーが対応しているもの）
+    pub async fn check_transfer(&self, protocol: i32, prober: &Prober) -> Result<Option<bool>> {
+        let semaphore = prober.ip_limiter.semaphore(self.resolved.ip());
+        let _ip_permit = semaphore.acquire_owned().await?;
+        let mut stream = timeout(Duration::from_secs(3), TcpStream::connect(self.resolved))
+            .await
+            .context("connect timeout")??;
+        let op_timeout = Duration::from_secs(5);
+
+        let packet = handshake_packet(&self.host, self.port_effective, protocol, 3); // next state = transfer
+        timeout(op_timeout, stream.write_all(&packet)).await??;
+
+        // Login Start（名前 + UUID）
+        let mut payload = vec![0x00];
+        write_mc_string(TRANSFER_CHECK_NAME, &mut payload);
+        payload.extend_from_slice(&[0; 16]);
+        let mut packet = Vec::new();
+        write_varint(payload.len() as i32, &mut packet);
+        packet.extend_from_slice(&payload);
+        timeout(op_timeout, stream.write_all(&packet)).await??;
+
+        timeout(op_timeout, read_transfer_response(&mut stream)).await?
+    }
+}
+
+/// Login Startに対する最初の応答から、転送を受け付けるかを判断する
+/// 転送が無効だという理由で切断されたらSome(false)、ログインが進んだらSome(true)
+/// それ以外の理由で切断された場合は判断できないのでNone
+async fn read_transfer_response<R: AsyncRead + Unpin>(r: &mut R) -> Result<Option<bool>> {
+    let _len = read_varint(r).await?;
+    let pid = read_varint(r).await?;
+    match pid {
+        // Disconnect
+        0x00 => {
+            let reason = read_mc_string_bytes(r, MAX_DISCONNECT_REASON_BYTES).await?;
+            let reason = String::from_utf8_lossy(&reason);
+            Ok(reason
+                .contains("multiplayer.disconnect.transfers_disabled")
+                .then_some(false))
+        }
+        // Encryption Request / Login Success / Set Compression / Login Plugin Request
+        0x01..=0x04 => Ok(Some(true)),
+        _ => anyhow::bail!("Unexpected login packet id {pid}"),
+    }
+}
+
+/// 転送の確認でLogin Startに使うプレイヤー名
+const TRANSFER_CHECK_NAME: &str = "ServerListCheck";
+
+/// Handshakeパケット（長さ付き）
+/// server address には元のホスト名、port には実際に接続したポートを入れる
+fn handshake_packet(host: &str, port: u16, protocol: i32, next_state: i32) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.push(0x00); // packet id
+    write_varint(protocol, &mut payload);
+    write_mc_string(host, &mut payload);
+    payload.extend_from_slice(&port.to_be_bytes());
+    write_varint(next_state, &mut payload);
+
+    let mut packet = Vec::new();
+    write_varint(payload.len() as i32, &mut packet);
+    packet.extend_from_slice(&payload);
+    packet
 }
 
 /* ---------- SRV 対応の候補生成 ---------- */
@@ -202,12 +320,16 @@ async fn lookup_host_with_port(
 async fn connect_race_all(
     addrs: &[SocketAddr],
     per_attempt: Duration,
-) -> Result<(TcpStream, SocketAddr)> {
+    ip_limiter: &IpLimiter,
+) -> Result<(TcpStream, SocketAddr, OwnedSemaphorePermit)> {
     let mut set = JoinSet::new();
     for addr in addrs.iter().copied() {
+        let semaphore = ip_limiter.semaphore(addr.ip());
         set.spawn(async move {
+            // 同一IPへの接続が上限に達していれば空くまで待つ（タイムアウトには含めない）
+            let permit = semaphore.acquire_owned().await.map_err(io::Error::other)?;
             match timeout(per_attempt, TcpStream::connect(addr)).await {
-                Ok(Ok(s)) => Ok::<_, io::Error>((s, addr)),
+                Ok(Ok(s)) => Ok::<_, io::Error>((s, addr, permit)),
                 Ok(Err(e)) => Err(e),
                 Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "connect timeout")),
             }
@@ -218,19 +340,18 @@ async fn connect_race_all(
 
     while let Some(join_result) = set.join_next().await {
         match join_result {
-            Ok(Ok((stream, addr))) => {
+            Ok(Ok((stream, addr, permit))) => {
                 set.abort_all();
-                return Ok((stream, addr));
+                return Ok((stream, addr, permit));
             }
             Ok(Err(e)) => last_err = Some(e),
-            Err(join_err) => last_err = Some(io::Error::new(io::ErrorKind::Other, join_err)),
+            Err(join_err) => last_err = Some(io::Error::other(join_err)),
         }
     }
 
-    Err(anyhow::anyhow!(last_err.unwrap_or_else(|| io::Error::new(
-        io::ErrorKind::Other,
-        "no addresses to try"
-    ))))
+    Err(anyhow::anyhow!(
+        last_err.unwrap_or_else(|| io::Error::other("no addresses to try"))
+    ))
     .context("All addresses failed concurrently")
 }
 
@@ -290,6 +411,25 @@ async fn read_exact_n<R: AsyncRead + Unpin>(r: &mut R, len: usize) -> io::Result
     Ok(buf)
 }
 
+/// 長さ付きの文字列をバイト列のまま読む
+/// 長さは相手のサーバーが決めるので、負の値や `max_len` を超える値は確保する前に弾く
+async fn read_mc_string_bytes<R: AsyncRead + Unpin>(
+    r: &mut R,
+    max_len: usize,
+) -> io::Result<Vec<u8>> {
+    let len = read_varint(r).await?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= max_len)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid string length {len} (max {max_len})"),
+            )
+        })?;
+    read_exact_n(r, len).await
+}
+
 impl fmt::Display for MinecraftServerInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // 必要なら MOTD の改行を潰すなどの整形をここで行う
@@ -360,6 +500,145 @@ fn description_to_text(desc: &Description) -> String {
     }
 }
 
+fn description_to_json(desc: &Description) -> String {
+    match desc {
+        Description::Text(s) => serde_json::Value::String(s.clone()).to_string(),
+        Description::Obj(v) => v.to_string(),
+    }
+}
+
+fn description_to_legacy(desc: &Description) -> String {
+    match desc {
+        Description::Text(s) => s.clone(),
+        Description::Obj(v) => {
+            let mut writer = LegacyWriter::default();
+            writer.write(v, &LegacyStyle::default());
+            writer.out
+        }
+    }
+}
+
+/* ---------- JSON の書式 → 書式コード ---------- */
+
+/// テキストコンポーネントの書式 (親から引き継ぐ)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LegacyStyle {
+    color: Option<char>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+    obfuscated: bool,
+}
+
+impl LegacyStyle {
+    fn inherit(&self, map: &serde_json::Map<String, serde_json::Value>) -> Self {
+        let flag =
+            |name: &str, parent: bool| map.get(name).and_then(|x| x.as_bool()).unwrap_or(parent);
+        Self {
+            // #rrggbb の色は書式コードで表せないので親の色のまま
+            color: map
+                .get("color")
+                .and_then(|x| x.as_str())
+                .and_then(color_code)
+                .or(self.color),
+            bold: flag("bold", self.bold),
+            italic: flag("italic", self.italic),
+            underlined: flag("underlined", self.underlined),
+            strikethrough: flag("strikethrough", self.strikethrough),
+            obfuscated: flag("obfuscated", self.obfuscated),
+        }
+    }
+
+    fn codes(&self) -> String {
+        // 色コードは装飾を解除するので、色 → 装飾の順に並べる
+        let mut codes = String::from("§r");
+        if let Some(color) = self.color {
+            codes.push('§');
+            codes.push(color);
+        }
+        for (enabled, code) in [
+            (self.obfuscated, 'k'),
+            (self.bold, 'l'),
+            (self.strikethrough, 'm'),
+            (self.underlined, 'n'),
+            (self.italic, 'o'),
+        ] {
+            if enabled {
+                codes.push('§');
+                codes.push(code);
+            }
+        }
+        codes
+    }
+}
+
+#[derive(Default)]
+struct LegacyWriter {
+    out: String,
+    current: LegacyStyle,
+}
+
+impl LegacyWriter {
+    fn write(&mut self, v: &serde_json::Value, parent: &LegacyStyle) {
+        match v {
+            serde_json::Value::String(s) => self.push(parent, s),
+            serde_json::Value::Object(map) => {
+                let style = parent.inherit(map);
+                if let Some(t) = map.get("text").and_then(|x| x.as_str()) {
+                    self.push(&style, t);
+                }
+                if let Some(arr) = map.get("extra").and_then(|x| x.as_array()) {
+                    for item in arr {
+                        self.write(item, &style);
+                    }
+                }
+            }
+            serde_json::Value::Array(arr) => {
+                for item in arr {
+                    self.write(item, parent);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, style: &LegacyStyle, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        // 書式が変わるところだけコードを入れる
+        if *style != self.current {
+            self.out.push_str(&style.codes());
+            self.current = style.clone();
+        }
+        self.out.push_str(text);
+    }
+}
+
+fn color_code(name: &str) -> Option<char> {
+    let code = match name {
+        "black" => '0',
+        "dark_blue" => '1',
+        "dark_green" => '2',
+        "dark_aqua" => '3',
+        "dark_red" => '4',
+        "dark_purple" => '5',
+        "gold" => '6',
+        "gray" => '7',
+        "dark_gray" => '8',
+        "blue" => '9',
+        "green" => 'a',
+        "aqua" => 'b',
+        "red" => 'c',
+        "light_purple" => 'd',
+        "yellow" => 'e',
+        "white" => 'f',
+        _ => return None,
+    };
+    Some(code)
+}
+
 fn extract_text(v: &serde_json::Value) -> String {
     match v {
         serde_json::Value::String(s) => s.clone(),
@@ -379,3 +658,93 @@ fn extract_text(v: &serde_json::Value) -> String {
         _ => String::new(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 長さ付きのパケット
+    fn packet(id: i32, body: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        write_varint(id, &mut payload);
+        payload.extend_from_slice(body);
+        let mut packet = Vec::new();
+        write_varint(payload.len() as i32, &mut packet);
+        packet.extend_from_slice(&payload);
+        packet
+    }
+
+    fn disconnect(reason: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        write_mc_string(reason, &mut body);
+        packet(0x00, &body)
+    }
+
+    #[tokio::test]
+    async fn reads_strings_within_the_limit() {
+        let mut bytes = Vec::new();
+        write_mc_string("hello", &mut bytes);
+        let text = read_mc_string_bytes(&mut bytes.as_slice(), 5)
+            .await
+            .unwrap();
+        assert_eq!(text, b"hello");
+
+        let error = read_mc_string_bytes(&mut bytes.as_slice(), 4)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn rejects_negative_and_truncated_strings() {
+        let mut bytes = Vec::new();
+        write_varint(-1, &mut bytes);
+        let error = read_mc_string_bytes(&mut bytes.as_slice(), usize::MAX)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut bytes = Vec::new();
+        write_varint(10, &mut bytes);
+        bytes.extend_from_slice(b"short");
+        let error = read_mc_string_bytes(&mut bytes.as_slice(), 100)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn transfers_disabled_means_not_accepted() {
+        let bytes = disconnect(r#"{"translate":"multiplayer.disconnect.transfers_disabled"}"#);
+        let accepted = read_transfer_response(&mut bytes.as_slice()).await.unwrap();
+        assert_eq!(accepted, Some(false));
+    }
+
+    #[tokio::test]
+    async fn other_disconnects_are_unknown() {
+        let bytes = disconnect(r#"{"text":"You are not whitelisted on this server!"}"#);
+        let accepted = read_transfer_response(&mut bytes.as_slice()).await.unwrap();
+        assert_eq!(accepted, None);
+    }
+
+    #[tokio::test]
+    async fn login_progress_means_accepted() {
+        for id in 0x01..=0x04 {
+            let bytes = packet(id, &[0; 8]);
+            let accepted = read_transfer_response(&mut bytes.as_slice()).await.unwrap();
+            assert_eq!(accepted, Some(true), "packet id {id:#04x}");
+        }
+    }
+
+    #[tokio::test]
+    async fn unexpected_packets_are_errors() {
+        let bytes = packet(0x05, &[]);
+        assert!(read_transfer_response(&mut bytes.as_slice()).await.is_err());
+
+        // 長すぎる切断理由は読まずに弾く
+        let mut body = Vec::new();
+        write_varint(MAX_DISCONNECT_REASON_BYTES as i32 + 1, &mut body);
+        let bytes = packet(0x00, &body);
+        assert!(read_transfer_response(&mut bytes.as_slice()).await.is_err());
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::{Edition, LocalizedText, ServerConfig},
+    health::Health,
+    service::MinecraftServerStatus,
+    stats::{PlayerStats, UptimeStats},
+    storage::PlayerSummary,
+};
+
+/// ネットワークに属する入口1つ分の状態
+#[derive(Debug, Clone)]
+pub struct EndpointStatus {
+    /// [`ServerConfig::key`]
+    pub key: String,
+    /// 入口の名前
+    pub name: LocalizedText,
+    pub ip: String,
+    pub port: i32,
+    pub health: Health,
+    pub players_online: i32,
+    pub players_max: i32,
+    /// pingの往復時間 (ミリ秒, オフラインならNone)
+    pub latency_ms: Option<i64>,
+    pub version_name: String,
+    pub version_protocol: i32,
+    pub editions: Vec<Edition>,
+    /// Transferパケットで転送できるか (不明ならNone)
+    pub transferable: Option<bool>,
+    /// 転送先に選ばれたか
+    pub is_best: bool,
+}
+
+/// ## servers.tomlのネットワークの構成
+///
+/// * 同じネットワークのエントリーはリスト上で1つにまとめる
+/// * まとめた項目のキーとIDはネットワークのIDにし、入口の状態が変わっても変わらないようにする
+/// * 名前・説明欄・アイコン・タグなどservers.tomlに書く情報と詳細の履歴は、
+///   servers.tomlで最初に書かれたエントリー (まだpingしていなくても) のものを使う
+/// * 接続先 (ip・port) とバージョンは最も状態の良い入口のものにし、そのまま転送に使えるようにする
+///   稼働状態、転送できるか、pingの往復時間の順に比べる
+/// * プレイヤー人数は入口ごとの人数の合計で、プレイヤーを共有する入口は1つ分だけ数える
+///   集計済みの人数 (平均・最大・中央値) も同じように、共有する入口の中で最も多いものを合計する
+/// * 稼働率などはどれかの入口に入れればよいので、項目ごとに最も良い入口の値を使う
+///   勢いも最も伸びている入口の値にする
+#[derive(Default)]
+pub struct Networks {
+    /// ネットワークのIDから所属するエントリーのキー (servers.tomlの順)
+    members: HashMap<String, Vec<String>>,
+    /// エントリーのキーから所属するネットワークのID
+    network_of: HashMap<String, String>,
+    /// エントリーのキーからプレイヤーを共有するグループ
+    player_pools: HashMap<String, String>,
+}
+
+impl Networks {
+    pub fn new(servers: &[ServerConfig]) -> Self {
+        let mut networks = Self::default();
+        for config in servers.iter() {
+            let Some(network) = &config.network else {
+                continue;
+            };
+            let key = config.key();
+            networks
+                .members
+                .entry(network.clone())
+                .or_default()
+                .push(key.clone());
+            networks.network_of.insert(key.clone(), network.clone());
+            if let Some(pool) = &config.player_pool {
+                networks.player_pools.insert(key, pool.clone());
+            }
+        }
+        networks
+    }
+
+    /// リスト上でエントリーをまとめた項目のキー
+    /// ネットワークに属していればネットワークのID、そうでなければ自身のキー
+    pub fn listing_key<'a>(&'a self, key: &'a str) -> &'a str {
+        self.network_of.get(key).map(String::as_str).unwrap_or(key)
+    }
+
+    /// ネットワークに所属するエントリーのキー (servers.tomlの順)
+    /// ネットワークでなければNone
+    pub fn members(&self, network: &str) -> Option<&[String]> {
+        self.members.get(network).map(Vec::as_slice)
+    }
+
+    /// ネットワークの入口 (servers.tomlの順) を1つの項目にまとめる
+    /// `primary` はservers.tomlで最初に書かれたエントリー、`members` はpingした入口
+    /// 入口が1つもなければNone
+    pub fn merge(
+        &self,
+        network: &str,
+        primary: &MinecraftServerStatus,
+        members: Vec<MinecraftServerStatus>,
+    ) -> Option<MinecraftServerStatus> {
+        let best = members
+            .iter()
+            .enumerate()
+            .min_by_key(|(index, status)| {
+                let health = match status.health {
+                    Health::Online => 0,
+                    Health::Suspect => 1,
+                    Health::Maintenance => 2,
+                    Health::Offline => 3,
+                };
+                // 転送できると分かっているもの、不明なもの、できないものの順
+                let transferable = match status.transferable {
+                    Some(true) => 0,
+                    None => 1,
+                    Some(false) => 2,
+                };
+                (
+                    health,
+                    transferable,
+                    status.latency_ms.unwrap_or(i64::MAX),
+                    *index,
+                )
+            })
+            .map(|(index, _)| index)?;
+
+        // プレイヤーを共有する入口は人数の多い方だけを数える
+        let mut pools = HashMap::<&str, (i32, i32)>::new();
+        for status in members.iter().filter(|status| status.is_online) {
+            let (online, max) = pools.entry(self.player_pool(&status.key)).or_default();
+            *online = (*online).max(status.players_online);
+            *max = (*max).max(status.players_max);
+        }
+        let players = PlayerStats {
+            daily: self.sum_summaries(&members, |players| players.daily),
+            weekly: self.sum_summaries(&members, |players| players.weekly),
+        };
+
+        let endpoints = members
+            .iter()
+            .enumerate()
+            .map(|(index, status)| EndpointStatus {
+                key: status.key.clone(),
+                name: status.name.clone(),
+                ip: status.ip.clone(),
+                port: status.port,
+                health: status.health,
+                players_online: status.players_online,
+                players_max: status.players_max,
+                latency_ms: status.latency_ms,
+                version_name: status.version_name.clone(),
+                version_protocol: status.version_protocol,
+                editions: status.editions.clone(),
+                transferable: status.transferable,
+                is_best: index == best,
+            })
+            .collect();
+        let mut editions = Vec::new();
+        for edition in members.iter().flat_map(|status| status.editions.iter()) {
+            if !editions.contains(edition) {
+                editions.push(*edition);
+            }
+        }
+
+        let best = &members[best];
+        Some(MinecraftServerStatus {
+            key: network.to_string(),
+            id: network.to_string(),
+            ip: best.ip.clone(),
+            port: best.port,
+            is_online: best.is_online,
+            health: best.health,
+            version_name: best.version_name.clone(),
+            version_protocol: best.version_protocol,
+            software: best.software,
+            latency_ms: best.latency_ms,
+            offline_reason: best.offline_reason,
+            transferable: best.transferable,
+            players_online: pools.values().map(|(online, _)| online).sum(),
+            players_max: pools.values().map(|(_, max)| max).sum(),
+            players,
+            uptime: best_uptime(&members),
+            trending_score: members
+                .iter()
+                .filter_map(|status| status.trending_score)
+                .reduce(f64::max),
+            editions,
+            endpoints,
+            ..primary.clone()
+        })
+    }
+
+    /// プレイヤーを共有するグループ (指定がなければ入口ごとに別)
+    fn player_pool<'a>(&'a self, key: &'a str) -> &'a str {
+        self.player_pools
+            .get(key)
+            .map(String::as_str)
+            .unwrap_or(key)
+    }
+
+    /// 入口ごとの集計済みの人数を、プレイヤーを共有する入口の中では最大を取って合計する
+    /// 集計のある入口がなければNone
+    fn sum_summaries(
+        &self,
+        members: &[MinecraftServerStatus],
+        summary: impl Fn(&PlayerStats) -> Option<PlayerSummary>,
+    ) -> Option<PlayerSummary> {
+        let mut pools = HashMap::<&str, PlayerSummary>::new();
+        for status in members.iter() {
+            let Some(summary) = summary(&status.players) else {
+                continue;
+            };
+            pools
+                .entry(self.player_pool(&status.key))
+                .and_modify(|pool| {
+                    pool.average = pool.average.max(summary.average);
+                    pool.peak = pool.peak.max(summary.peak);
+                    pool.median = pool.median.max(summary.median);
+                })
+                .or_insert(summary);
+        }
+        pools.into_values().reduce(|total, pool| PlayerSummary {
+            average: total.average + pool.average,
+            peak: total.peak + pool.peak,
+            median: total.median + pool.median,
+        })
+    }
+}
+
+/// 項目ごとに最も良い入口の稼働状況
+fn best_uptime(members: &[MinecraftServerStatus]) -> UptimeStats {
+    let max = |value: fn(&UptimeStats) -> Option<f64>| {
+        members
+            .iter()
+            .filter_map(|status| value(&status.uptime))
+            .reduce(f64::max)
+    };
+    UptimeStats {
+        uptime_24h: max(|uptime| uptime.uptime_24h),
+        uptime_7d: max(|uptime| uptime.uptime_7d),
+        uptime_30d: max(|uptime| uptime.uptime_30d),
+        last_seen_online: members
+            .iter()
+            .filter_map(|status| status.uptime.last_seen_online)
+            .max(),
+        longest_outage_secs: members
+            .iter()
+            .map(|status| status.uptime.longest_outage_secs)
+            .min()
+            .unwrap_or(0),
+        mtbf_secs: members
+            .iter()
+            .filter_map(|status| status.uptime.mtbf_secs)
+            .max(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{offline_status, online_status, player_summary, server_config};
+
+    /// ネットワーク `net` の入口
+    fn member(host: &str, pool: Option<&str>) -> ServerConfig {
+        let pool = pool
+            .map(|pool| format!("player_pool = \"{pool}\""))
+            .unwrap_or_default();
+        server_config(&format!(
+            "ip = \"{host}.example.com\"\nnetwork = \"net\"\n{pool}"
+        ))
+    }
+
+    #[test]
+    fn shared_pools_count_once() {
+        let configs = [
+            member("a", Some("main")),
+            member("b", Some("main")),
+            member("c", None),
+        ];
+        let networks = Networks::new(&configs);
+        let mut a = online_status(&configs[0], 10);
+        a.players_max = 100;
+        let mut b = online_status(&configs[1], 30);
+        b.players_max = 200;
+        let mut c = online_status(&configs[2], 5);
+        c.players_max = 50;
+
+        let merged = networks.merge("net", &a.clone(), vec![a, b, c]).unwrap();
+        assert_eq!(merged.players_online, 30 + 5);
+        assert_eq!(merged.players_max, 200 + 50);
+        assert_eq!(merged.key, "net");
+        assert_eq!(merged.id, "net");
+        assert_eq!(merged.endpoints.len(), 3);
+    }
+
+    #[test]
+    fn offline_members_do_not_count() {
+        let configs = [member("a", None), member("b", None)];
+        let networks = Networks::new(&configs);
+        let a = online_status(&configs[0], 10);
+        let mut b = offline_status(&configs[1]);
+        b.players_online = 99;
+
+        let merged = networks.merge("net", &a.clone(), vec![a, b]).unwrap();
+        assert_eq!(merged.players_online, 10);
+        assert_eq!(merged.players_max, 100);
+    }
+
+    #[test]
+    fn summaries_take_the_pool_maximum_and_sum_pools() {
+        let configs = [
+            member("a", Some("main")),
+            member("b", Some("main")),
+            member("c", None),
+        ];
+        let networks = Networks::new(&configs);
+        let mut a = online_status(&configs[0], 0);
+        a.players.weekly = Some(player_summary(10.0, 40, 8.0));
+        let mut b = online_status(&configs[1], 0);
+        b.players.weekly = Some(player_summary(20.0, 30, 15.0));
+        b.players.daily = Some(player_summary(4.0, 6, 3.0));
+        let mut c = online_status(&configs[2], 0);
+        c.players.weekly = Some(player_summary(5.0, 9, 4.0));
+
+        let merged = networks.merge("net", &a.clone(), vec![a, b, c]).unwrap();
+        let weekly = merged.players.weekly.unwrap();
+        assert_eq!(weekly.average, 20.0 + 5.0);
+        assert_eq!(weekly.peak, 40 + 9);
+        assert_eq!(weekly.median, 15.0 + 4.0);
+        let daily = merged.players.daily.unwrap();
+        assert_eq!((daily.average, daily.peak, daily.median), (4.0, 6, 3.0));
+    }
+
+    #[test]
+    fn takes_the_best_uptime_and_trending() {
+        let configs = [member("a", None), member("b", None)];
+        let networks = Networks::new(&configs);
+        let mut a = online_status(&configs[0], 0);
+        a.uptime = UptimeStats {
+            uptime_24h: Some(1.0),
+            uptime_7d: Some(0.5),
+            uptime_30d: None,
+            last_seen_online: Some(100),
+            longest_outage_secs: 600,
+            mtbf_secs: None,
+        };
+        a.trending_score = Some(0.2);
+        let mut b = online_status(&configs[1], 0);
+        b.uptime = UptimeStats {
+            uptime_24h: Some(0.8),
+            uptime_7d: Some(0.9),
+            uptime_30d: Some(0.7),
+            last_seen_online: Some(200),
+            longest_outage_secs: 60,
+            mtbf_secs: Some(3600),
+        };
+        b.trending_score = Some(0.5);
+
+        let merged = networks.merge("net", &a.clone(), vec![a, b]).unwrap();
+        assert_eq!(merged.uptime.uptime_24h, Some(1.0));
+        assert_eq!(merged.uptime.uptime_7d, Some(0.9));
+        assert_eq!(merged.uptime.uptime_30d, Some(0.7));
+        assert_eq!(merged.uptime.last_seen_online, Some(200));
+        assert_eq!(merged.uptime.longest_outage_secs, 60);
+        assert_eq!(merged.uptime.mtbf_secs, Some(3600));
+        assert_eq!(merged.trending_score, Some(0.5));
+    }
+
+    #[test]
+    fn connects_to_the_best_endpoint_and_describes_with_the_primary() {
+        let mut configs = [member("a", None), member("b", None), member("c", None)];
+        configs[0].name = LocalizedText::Plain("Primary".to_string());
+        let networks = Networks::new(&configs);
+        // 最初のエントリーはまだpingしていない
+        let primary = offline_status(&configs[0]);
+        let mut b = online_status(&configs[1], 0);
+        b.latency_ms = Some(80);
+        let mut c = online_status(&configs[2], 0);
+        c.latency_ms = Some(20);
+
+        let merged = networks.merge("net", &primary, vec![b, c]).unwrap();
+        assert_eq!(merged.name.get(None), "Primary");
+        assert_eq!(merged.ip, "c.example.com");
+        assert!(merged.is_online);
+        assert_eq!(
+            merged
+                .endpoints
+                .iter()
+                .map(|endpoint| (endpoint.key.as_str(), endpoint.is_best))
+                .collect::<Vec<_>>(),
+            vec![("b.example.com", false), ("c.example.com", true)]
+        );
+    }
+
+    #[test]
+    fn no_members_no_listing() {
+        let configs = [member("a", None)];
+        let networks = Networks::new(&configs);
+        let primary = offline_status(&configs[0]);
+        assert!(networks.merge("net", &primary, Vec::new()).is_none());
+        assert_eq!(networks.listing_key("a.example.com"), "net");
+        assert_eq!(
+            networks.listing_key("other.example.com"),
+            "other.example.com"
+        );
+    }
+}
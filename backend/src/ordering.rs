@@ -0,0 +1,213 @@
+use std::{cmp::Reverse, sync::Arc};
+
+use anyhow::anyhow;
+use icu_collator::{Collator, CollatorOptions};
+
+use crate::{service::MinecraftServerStatus, text::strip_formatting, version::latest_version};
+
+/// サーバーリストの並び替えの基準
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortKey {
+    /// 現在のプレイヤー人数
+    Players,
+    /// 直近30日間の稼働率
+    Uptime,
+    /// 7日間の平均プレイヤー人数
+    WeeklyAveragePlayers,
+    /// 勢い
+    Trending,
+    /// 複合スコア
+    Ranked,
+    /// ローテーションの区間ごとの疑似乱数
+    Shuffle,
+    /// 名前 (読みがあれば読み)
+    Name,
+    /// pingの往復時間
+    Latency,
+    /// 対応している最も新しいバージョン
+    Version,
+    /// servers.tomlに追加された日時
+    RecentlyAdded,
+}
+
+/// 並び替えの向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 10] = [
+        Self::Players,
+        Self::Uptime,
+        Self::WeeklyAveragePlayers,
+        Self::Trending,
+        Self::Ranked,
+        Self::Shuffle,
+        Self::Name,
+        Self::Latency,
+        Self::Version,
+        Self::RecentlyAdded,
+    ];
+
+    /// 向きを指定しなかったときの向き
+    pub fn default_direction(self) -> Direction {
+        match self {
+            Self::Shuffle | Self::Name | Self::Latency => Direction::Ascending,
+            _ => Direction::Descending,
+        }
+    }
+}
+
+/// 並び替え済みのサーバーリスト
+/// 基準となる値がないサーバー (オフラインで応答時間がない等) は向きに関係なく最後に置く
+pub struct SortedStatuses {
+    /// 既定の向きで並べたもの
+    statuses: Vec<Arc<MinecraftServerStatus>>,
+    /// 基準となる値があるサーバーの数
+    ranked: usize,
+    default_direction: Direction,
+}
+
+impl SortedStatuses {
+    pub fn iter(
+        &self,
+        direction: Direction,
+    ) -> Box<dyn Iterator<Item = &Arc<MinecraftServerStatus>> + Send + '_> {
+        let (ranked, unranked) = self.statuses.split_at(self.ranked);
+        if direction == self.default_direction {
+            Box::new(ranked.iter().chain(unranked))
+        } else {
+            Box::new(ranked.iter().rev().chain(unranked))
+        }
+    }
+}
+
+/// 名前の照合順序などを持つ並び替え担当
+pub struct Sorter {
+    /// 日本語の照合順序 (かなは五十音順、清音・濁音・半濁音の順)
+    collator: Collator,
+}
+
+impl Sorter {
+    pub fn new() -> anyhow::Result<Self> {
+        let collator = Collator::try_new(
+            &"ja".parse().map_err(|error| anyhow!("{error}"))?,
+            CollatorOptions::new(),
+        )
+        .map_err(|error| anyhow!("Failed to load the collation data : {error}"))?;
+        Ok(Self { collator })
+    }
+
+    /// `statuses` を `key` の既定の向きで並べる
+    /// 同順位のものは `statuses` での順番を保つ
+    pub fn sort(&self, statuses: &[Arc<MinecraftServerStatus>], key: SortKey) -> SortedStatuses {
+        let (mut ranked, unranked): (Vec<_>, Vec<_>) = statuses
+            .iter()
+            .cloned()
+            .partition(|status| has_value(status, key));
+
+        match key {
+            SortKey::Players => ranked.sort_by_key(|status| Reverse(status.players_online)),
+            SortKey::Uptime => ranked.sort_by(|a, b| {
+                let a = a.uptime.uptime_30d.unwrap_or_default();
+                let b = b.uptime.uptime_30d.unwrap_or_default();
+                b.total_cmp(&a)
+            }),
+            SortKey::WeeklyAveragePlayers => ranked.sort_by(|a, b| {
+                let a = a.players.weekly.map(|weekly| weekly.average);
+                let b = b.players.weekly.map(|weekly| weekly.average);
+                b.unwrap_or_default().total_cmp(&a.unwrap_or_default())
+            }),
+            SortKey::Trending => ranked.sort_by(|a, b| {
+                let a = a.trending_score.unwrap_or_default();
+                let b = b.trending_score.unwrap_or_default();
+                b.total_cmp(&a)
+            }),
+            SortKey::Ranked => ranked.sort_by(|a, b| b.ranking.total.total_cmp(&a.ranking.total)),
+            SortKey::Shuffle => {}
+            SortKey::Name => {
+                // 比較のたびに書式コードを取り除かないよう先に作っておく
+                let mut keyed = ranked
+                    .into_iter()
+                    .map(|status| {
+                        // 並び順は言語ごとに持たないので、既定の言語の名前で並べる
+                        let name = status.reading.as_deref().unwrap_or(status.name.get(None));
+                        (strip_formatting(name), status)
+                    })
+                    .collect::<Vec<_>>();
+                keyed.sort_by(|(a, _), (b, _)| self.collator.compare(a, b));
+                ranked = keyed.into_iter().map(|(_, status)| status).collect();
+            }
+            SortKey::Latency => ranked.sort_by_key(|status| status.latency_ms),
+            SortKey::Version => {
+                let mut keyed = ranked
+                    .into_iter()
+                    .map(|status| (latest_version(&status.version_name), status))
+                    .collect::<Vec<_>>();
+                keyed.sort_by(|(a, a_status), (b, b_status)| {
+                    b.cmp(a)
+                        .then(b_status.version_protocol.cmp(&a_status.version_protocol))
+                });
+                ranked = keyed.into_iter().map(|(_, status)| status).collect();
+            }
+            SortKey::RecentlyAdded => ranked.sort_by_key(|status| Reverse(status.added_at)),
+        }
+
+        let count = ranked.len();
+        ranked.extend(unranked);
+        SortedStatuses {
+            statuses: ranked,
+            ranked: count,
+            default_direction: key.default_direction(),
+        }
+    }
+}
+
+/// 並び替えの基準となる値があるか
+fn has_value(status: &MinecraftServerStatus, key: SortKey) -> bool {
+    match key {
+        SortKey::Players | SortKey::Ranked | SortKey::Shuffle | SortKey::Name => true,
+        SortKey::Uptime => status.uptime.uptime_30d.is_some(),
+        SortKey::WeeklyAveragePlayers => status.players.weekly.is_some(),
+        SortKey::Trending => status.trending_score.is_some(),
+        SortKey::Latency => status.latency_ms.is_some(),
+        SortKey::Version => status.is_online && latest_version(&status.version_name).is_some(),
+        SortKey::RecentlyAdded => status.added_at.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{online_status, player_summary, server_config};
+
+    /// 7日間の平均プレイヤー人数が `average` のサーバー (Noneなら集計なし)
+    fn weekly(host: &str, average: Option<f64>) -> Arc<MinecraftServerStatus> {
+        let mut status = online_status(&server_config(&format!("ip = \"{host}\"")), 0);
+        status.players.weekly = average.map(|average| player_summary(average, 0, 0.0));
+        Arc::new(status)
+    }
+
+    fn keys(sorted: &SortedStatuses, direction: Direction) -> Vec<&str> {
+        sorted
+            .iter(direction)
+            .map(|status| status.key.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn weekly_average_players_puts_servers_without_rollups_last() {
+        let sorter = Sorter::new().unwrap();
+        let statuses = [
+            weekly("a", Some(5.0)),
+            weekly("b", None),
+            weekly("c", Some(20.0)),
+            weekly("d", Some(0.5)),
+        ];
+        let sorted = sorter.sort(&statuses, SortKey::WeeklyAveragePlayers);
+        assert_eq!(keys(&sorted, Direction::Descending), ["c", "a", "d", "b"]);
+        assert_eq!(keys(&sorted, Direction::Ascending), ["d", "a", "c", "b"]);
+    }
+}
@@ -0,0 +1,207 @@
+use std::time::Duration;
+
+use crate::{config::RankingConfig, service::MinecraftServerStatus};
+
+/// 応答時間の要素が0.5になる応答時間 (ミリ秒)
+const LATENCY_HALF_MS: f64 = 100.0;
+
+/// Rankedの順序のスコアと、その内訳
+/// 内訳は各要素に重みを掛けたもので、合計がtotalになる
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RankingScore {
+    pub total: f64,
+    pub players: f64,
+    pub average_players: f64,
+    pub uptime: f64,
+    pub latency: f64,
+    pub boost: f64,
+}
+
+/// ## Rankedの順序のスコアの計算
+///
+/// * 人数は対数を取り、大きなサーバーが他の要素に関係なく上位を占めないようにする
+/// * 稼働率や平均人数のデータがないサーバーはその要素を0とする
+/// * オフラインのサーバーは現在の人数と応答時間の要素が0になる
+pub struct RankingPolicy {
+    weights: RankingConfig,
+}
+
+impl RankingPolicy {
+    pub fn new(config: &RankingConfig) -> Self {
+        Self {
+            weights: config.clone(),
+        }
+    }
+
+    pub fn score(&self, status: &MinecraftServerStatus) -> RankingScore {
+        let weights = &self.weights;
+        let average_players = status
+            .players
+            .weekly
+            .map(|weekly| weekly.average)
+            .unwrap_or(0.0);
+        let latency = status
+            .latency_ms
+            .map(|latency| LATENCY_HALF_MS / (LATENCY_HALF_MS + latency as f64))
+            .unwrap_or(0.0);
+
+        let mut score = RankingScore {
+            total: 0.0,
+            players: weights.players * (status.players_online.max(0) as f64).ln_1p(),
+            average_players: weights.average_players * average_players.max(0.0).ln_1p(),
+            uptime: weights.uptime * status.uptime.uptime_30d.unwrap_or(0.0),
+            latency: weights.latency * latency,
+            boost: weights.boost * status.boost,
+        };
+        score.total =
+            score.players + score.average_players + score.uptime + score.latency + score.boost;
+        score
+    }
+}
+
+/// ## 同順位のサーバーの並びのローテーション
+///
+/// * 時間を一定の長さの区間に分け、区間ごとに決まる疑似乱数の順で同順位のサーバーを並べる
+/// * 同じ区間の中では誰がいつ取得しても同じ並びになる
+/// * servers.tomlで先に書かれたサーバーが常に上に来ることがなくなり、1日を通して露出が均等になる
+pub struct Rotation {
+    window: i64,
+}
+
+impl Rotation {
+    pub fn new(config: &RankingConfig) -> Self {
+        Self {
+            window: config.rotation_window_secs.max(1) as i64,
+        }
+    }
+
+    /// `now` (UNIX時間, 秒) の属する区間の番号
+    pub fn seed(&self, now: i64) -> u64 {
+        now.div_euclid(self.window) as u64
+    }
+
+    /// 次の区間が始まるまでの時間
+    pub fn until_next_window(&self, now: i64) -> Duration {
+        Duration::from_secs((self.window - now.rem_euclid(self.window)) as u64)
+    }
+
+    /// 区間 `seed` でのサーバーの並び順のキー
+    /// 再起動やRustのバージョンに関係なく同じ値になるよう、ハッシュは自前で計算する
+    pub fn key(&self, seed: u64, server: &str) -> u64 {
+        // FNV-1a
+        let mut hash = 0xcbf29ce484222325u64;
+        for byte in seed.to_le_bytes().iter().chain(server.as_bytes()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+
+        // splitmix64の仕上げで、似たキー同士の偏りをなくす
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::testing::{offline_status, online_status, player_summary, server_config};
+
+    fn rotation(window: u64) -> Rotation {
+        Rotation::new(&RankingConfig {
+            rotation_window_secs: window,
+            ..RankingConfig::default()
+        })
+    }
+
+    fn order(rotation: &Rotation, seed: u64, servers: &[&str]) -> Vec<String> {
+        let mut servers = servers
+            .iter()
+            .map(|server| server.to_string())
+            .collect::<Vec<_>>();
+        servers.sort_by_cached_key(|server| rotation.key(seed, server));
+        servers
+    }
+
+    #[test]
+    fn seed_changes_per_window() {
+        let rotation = rotation(3600);
+        assert_eq!(rotation.seed(7200), rotation.seed(10799));
+        assert_ne!(rotation.seed(10799), rotation.seed(10800));
+        assert_eq!(rotation.until_next_window(7200), Duration::from_secs(3600));
+        assert_eq!(rotation.until_next_window(10799), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn key_is_stable() {
+        // 再起動しても同じ並びになるよう、値そのものが変わらないことを確かめる
+        let rotation = rotation(3600);
+        assert_eq!(rotation.key(0, "example.com"), 8612666357857288737);
+        assert_eq!(
+            rotation.key(0, "example.com"),
+            rotation.key(0, "example.com")
+        );
+        assert_ne!(
+            rotation.key(0, "example.com"),
+            rotation.key(1, "example.com")
+        );
+    }
+
+    #[test]
+    fn order_rotates_between_windows() {
+        let rotation = rotation(3600);
+        let servers = (0..20)
+            .map(|i| format!("server{i}.example.com"))
+            .collect::<Vec<_>>();
+        let servers = servers.iter().map(String::as_str).collect::<Vec<_>>();
+
+        assert_eq!(order(&rotation, 5, &servers), order(&rotation, 5, &servers));
+        assert_ne!(order(&rotation, 5, &servers), order(&rotation, 6, &servers));
+
+        // 先に書かれたサーバーがいつも先頭に来るわけではない
+        let firsts = (0..24)
+            .map(|seed| order(&rotation, seed, &servers)[0].clone())
+            .collect::<HashSet<_>>();
+        assert!(firsts.len() > 1);
+    }
+
+    #[test]
+    fn score_is_the_sum_of_weighted_factors() {
+        let policy = RankingPolicy::new(&RankingConfig {
+            players: 2.0,
+            average_players: 3.0,
+            uptime: 4.0,
+            latency: 5.0,
+            boost: 6.0,
+            ..RankingConfig::default()
+        });
+        let mut status = online_status(&server_config("ip = \"a\"\nboost = 0.5"), 9);
+        status.players.weekly = Some(player_summary(3.0, 10, 3.0));
+        status.uptime.uptime_30d = Some(0.5);
+        status.latency_ms = Some(100);
+
+        let score = policy.score(&status);
+        assert_eq!(score.players, 2.0 * 9f64.ln_1p());
+        assert_eq!(score.average_players, 3.0 * 3f64.ln_1p());
+        assert_eq!(score.uptime, 2.0);
+        assert_eq!(score.latency, 2.5);
+        assert_eq!(score.boost, 3.0);
+        assert_eq!(
+            score.total,
+            score.players + score.average_players + score.uptime + score.latency + score.boost
+        );
+    }
+
+    #[test]
+    fn missing_data_scores_zero() {
+        let policy = RankingPolicy::new(&RankingConfig::default());
+        let score = policy.score(&offline_status(&server_config("ip = \"a\"")));
+        assert_eq!(score.players, 0.0);
+        assert_eq!(score.average_players, 0.0);
+        assert_eq!(score.uptime, 0.0);
+        assert_eq!(score.latency, 0.0);
+        assert_eq!(score.total, 0.0);
+    }
+}
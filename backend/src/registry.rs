@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use minisign_verify::{PublicKey, Signature};
+use tracing::warn;
+
+use crate::config::RegistryConfig;
+
+/// 最後に検証を通ったservers.tomlの保存先
+const CACHE_PATH: &str = "servers.toml";
+/// 保存したservers.tomlの署名
+const CACHE_SIGNATURE_PATH: &str = "servers.toml.minisig";
+
+/// servers.tomlの取得と署名検証
+pub struct RegistryFetcher {
+    url: String,
+    refresh_interval: Duration,
+    signature_url: String,
+    public_keys: Vec<PublicKey>,
+}
+
+impl RegistryFetcher {
+    pub fn new(config: &RegistryConfig) -> Result<Self> {
+        let public_keys = config
+            .public_keys
+            .iter()
+            .map(|key| {
+                PublicKey::from_base64(key.trim())
+                    .map_err(|error| anyhow::anyhow!("{error}"))
+                    .with_context(|| format!("invalid minisign public key: {key}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            url: config.url.clone(),
+            refresh_interval: Duration::from_secs(config.refresh_interval_secs.max(1)),
+            signature_url: config
+                .signature_url
+                .clone()
+                .unwrap_or_else(|| format!("{}.minisig", config.url)),
+            public_keys,
+        })
+    }
+
+    /// servers.tomlを取得し直す間隔
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    /// 署名検証が有効かどうか
+    pub fn requires_signature(&self) -> bool {
+        !self.public_keys.is_empty()
+    }
+
+    /// servers.tomlをダウンロードし、公開鍵が設定されていれば署名を検証する
+    /// 検証に失敗したものは返さない
+    /// 検証を通ったものは署名と一緒に手元に保存し、起動時に取得できなかった場合に使う
+    pub async fn fetch(&self) -> Result<String> {
+        let bytes = download(&self.url)
+            .await
+            .with_context(|| format!("failed to download {}", self.url))?;
+
+        let signature = if self.requires_signature() {
+            let signature = download(&self.signature_url)
+                .await
+                .with_context(|| format!("failed to download {}", self.signature_url))?;
+            self.verify(&bytes, &signature)?;
+            Some(signature)
+        } else {
+            None
+        };
+
+        let source = String::from_utf8(bytes).context("servers.toml is not valid utf-8")?;
+        save_cache(&source, signature.as_deref()).await;
+        Ok(source)
+    }
+
+    /// 手元に保存したservers.tomlを読み込み、ダウンロードしたものと同じく署名を検証し直す
+    pub async fn load_cached(&self) -> Result<String> {
+        let bytes = tokio::fs::read(CACHE_PATH)
+            .await
+            .with_context(|| format!("failed to read {CACHE_PATH}"))?;
+
+        if self.requires_signature() {
+            let signature = tokio::fs::read(CACHE_SIGNATURE_PATH)
+                .await
+                .with_context(|| format!("failed to read {CACHE_SIGNATURE_PATH}"))?;
+            self.verify(&bytes, &signature)?;
+        }
+
+        String::from_utf8(bytes).context("servers.toml is not valid utf-8")
+    }
+
+    /// いずれかの公開鍵で検証できれば成功とする
+    fn verify(&self, bytes: &[u8], signature: &[u8]) -> Result<()> {
+        let signature = std::str::from_utf8(signature).context("signature is not valid utf-8")?;
+        let signature = Signature::decode(signature)
+            .map_err(|error| anyhow::anyhow!("malformed signature: {error}"))?;
+
+        let mut last_error = None;
+        for public_key in self.public_keys.iter() {
+            match public_key.verify(bytes, &signature, false) {
+                Ok(()) => return Ok(()),
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "signature verification failed: {}",
+            last_error
+                .map(|error| error.to_string())
+                .unwrap_or_else(|| "no public keys".to_string())
+        ))
+    }
+}
+
+/// 署名検証を通ったservers.tomlを保存する
+/// 署名がなければ古い署名を消し、別の内容と組み合わさらないようにする
+async fn save_cache(source: &str, signature: Option<&[u8]>) {
+    let result = match signature {
+        Some(signature) => tokio::fs::write(CACHE_SIGNATURE_PATH, signature).await,
+        None => match tokio::fs::remove_file(CACHE_SIGNATURE_PATH).await {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        },
+    };
+    if let Err(error) = result {
+        warn!("Faileed to write {} : {}", CACHE_SIGNATURE_PATH, error);
+    }
+    if let Err(error) = tokio::fs::write(CACHE_PATH, source).await {
+        warn!("Faileed to write {} : {}", CACHE_PATH, error);
+    }
+}
+
+async fn download(url: &str) -> Result<Vec<u8>> {
+    let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+    Ok(bytes.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// minisign-verifyのテストと同じ鍵と、"test" への署名
+    const PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const OTHER_PUBLIC_KEY: &str = "RWQBAgMEBQYHCCAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4/";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+    /// 事前にハッシュしない古い形式の署名
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==";
+
+    fn fetcher(public_keys: &[&str]) -> Result<RegistryFetcher> {
+        RegistryFetcher::new(&RegistryConfig {
+            url: "https://example.com/servers.toml".to_string(),
+            public_keys: public_keys.iter().map(|key| key.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn signature_url_defaults_next_to_the_registry() {
+        let fetcher = fetcher(&[]).unwrap();
+        assert_eq!(
+            fetcher.signature_url,
+            "https://example.com/servers.toml.minisig"
+        );
+        assert!(!fetcher.requires_signature());
+    }
+
+    #[test]
+    fn rejects_invalid_public_keys() {
+        assert!(fetcher(&["not a key"]).is_err());
+    }
+
+    #[test]
+    fn accepts_a_signature_from_any_configured_key() {
+        let fetcher = fetcher(&[OTHER_PUBLIC_KEY, PUBLIC_KEY]).unwrap();
+        assert!(fetcher.requires_signature());
+        assert!(fetcher.verify(b"test", SIGNATURE.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn rejects_modified_or_unknown_signatures() {
+        let other = fetcher(&[OTHER_PUBLIC_KEY]).unwrap();
+        assert!(other.verify(b"test", SIGNATURE.as_bytes()).is_err());
+
+        let fetcher = fetcher(&[PUBLIC_KEY]).unwrap();
+        assert!(fetcher.verify(b"Test", SIGNATURE.as_bytes()).is_err());
+        assert!(fetcher.verify(b"test", b"").is_err());
+        assert!(
+            fetcher
+                .verify(b"test", LEGACY_SIGNATURE.as_bytes())
+                .is_err()
+        );
+    }
+}
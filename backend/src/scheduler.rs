@@ -0,0 +1,327 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rand::Rng;
+use tokio::{
+    sync::{Notify, Semaphore},
+    time::{Instant, sleep_until},
+};
+
+use crate::config::SchedulerConfig;
+
+/// 使われていないセマフォを掃除し始めるエントリー数
+const IP_LIMITER_PRUNE_THRESHOLD: usize = 1024;
+
+/// 次回pingまでの間隔に加える揺らぎの割合
+const INTERVAL_JITTER_RATIO: f64 = 0.1;
+
+/// ## pingのスケジューラー
+///
+/// * サーバーごとに次回pingの時刻を持ち、早いものから順に取り出す
+/// * 人気のサーバーや人数の変動が大きいサーバーは短い間隔でpingする
+/// * 失敗が続くサーバーは間隔を指数的に伸ばす（上限あり）
+/// * 起動直後のpingは開始時刻をランダムに散らし、一斉に接続しないようにする
+/// * キューはサーバー1件につき1要素程度なので、登録数が多くてもメモリは登録数に比例する分で済む
+pub struct ProbeScheduler {
+    base_interval: Duration,
+    min_interval: Duration,
+    max_backoff: Duration,
+    initial_spread: Duration,
+    popular_players: i32,
+    volatile_players_delta: i32,
+    max_concurrent: usize,
+    queue: Mutex<BinaryHeap<Reverse<(Instant, String)>>>,
+    /// 予定が追加されたことを待機中のタスクに知らせる
+    wake: Notify,
+}
+
+/// 次回の間隔を決めるためのサーバーの状況
+pub struct PollState {
+    /// 連続で失敗した回数
+    pub consecutive_failures: u32,
+    /// 今回のプレイヤー人数
+    pub players_online: i32,
+    /// 前回のプレイヤー人数
+    pub previous_players_online: Option<i32>,
+}
+
+impl ProbeScheduler {
+    pub fn new(config: &SchedulerConfig) -> Self {
+        let base_interval = Duration::from_secs(config.base_interval_secs.max(1));
+        Self {
+            base_interval,
+            min_interval: Duration::from_secs(config.min_interval_secs.max(1)).min(base_interval),
+            max_backoff: Duration::from_secs(config.max_backoff_secs).max(base_interval),
+            initial_spread: base_interval.mul_f64(config.spread_ratio.clamp(0.0, 1.0)),
+            popular_players: config.popular_players,
+            volatile_players_delta: config.volatile_players_delta,
+            max_concurrent: config.max_concurrent_probes.max(1),
+            queue: Mutex::new(BinaryHeap::new()),
+            wake: Notify::new(),
+        }
+    }
+
+    /// 同時に実行するpingの最大数
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// `at` にpingするよう予定を入れる
+    pub fn schedule(&self, key: String, at: Instant) {
+        self.queue.lock().unwrap().push(Reverse((at, key)));
+        self.wake.notify_one();
+    }
+
+    /// 起動直後のpingの予定時刻
+    /// 基本の更新間隔の中に散らす
+    pub fn initial_time(&self) -> Instant {
+        let spread_ms = self.initial_spread.as_millis() as u64;
+        Instant::now() + Duration::from_millis(rand::rng().random_range(0..=spread_ms))
+    }
+
+    /// 次の予定時刻になるまで待ち、その予定を取り出す
+    /// 取り出すのは待機が終わった後なので、途中でキャンセルされても予定は失われない
+    pub async fn next_due(&self) -> (Instant, String) {
+        loop {
+            let earliest = self
+                .queue
+                .lock()
+                .unwrap()
+                .peek()
+                .map(|Reverse((at, _))| *at);
+
+            match earliest {
+                Some(at) if at <= Instant::now() => {
+                    let Reverse(entry) = self.queue.lock().unwrap().pop().unwrap();
+                    return entry;
+                }
+                Some(at) => {
+                    tokio::select! {
+                        _ = sleep_until(at) => {}
+                        _ = self.wake.notified() => {}
+                    }
+                }
+                None => self.wake.notified().await,
+            }
+        }
+    }
+
+    /// 今回のpingの結果から次回pingまでの間隔を決める
+    pub fn next_interval(&self, state: &PollState) -> Duration {
+        // 同じ間隔のサーバーが同時刻に集まらないよう揺らす
+        let jitter = rand::rng().random_range(-INTERVAL_JITTER_RATIO..=INTERVAL_JITTER_RATIO);
+        self.interval(state, jitter)
+    }
+
+    /// `jitter` (割合) だけ揺らした次回pingまでの間隔
+    /// 上限に達した間隔も短くする方には揺らし、揺らした後も上限を超えないようにする
+    fn interval(&self, state: &PollState, jitter: f64) -> Duration {
+        let interval = if state.consecutive_failures > 0 {
+            // 失敗が続くほど間隔を伸ばす
+            let exponent = (state.consecutive_failures - 1).min(16);
+            self.base_interval
+                .saturating_mul(1 << exponent)
+                .min(self.max_backoff)
+        } else {
+            let is_popular = state.players_online >= self.popular_players;
+            let is_volatile = state
+                .previous_players_online
+                .map(|previous| {
+                    (state.players_online - previous).abs() >= self.volatile_players_delta
+                })
+                .unwrap_or(false);
+
+            if is_popular || is_volatile {
+                self.min_interval
+            } else {
+                self.base_interval
+            }
+        };
+
+        interval.mul_f64(1.0 + jitter).min(self.max_backoff)
+    }
+}
+
+/// 接続先IPごとの同時接続数制限
+/// 同じホストに多数のエントリーが向いていても負荷をかけすぎないようにする
+pub struct IpLimiter {
+    max_per_ip: usize,
+    semaphores: Mutex<HashMap<IpAddr, Arc<Semaphore>>>,
+}
+
+impl IpLimiter {
+    pub fn new(max_per_ip: usize) -> Self {
+        Self {
+            max_per_ip: max_per_ip.max(1),
+            semaphores: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 指定IP用のセマフォを取得する
+    pub fn semaphore(&self, ip: IpAddr) -> Arc<Semaphore> {
+        let mut semaphores = self.semaphores.lock().unwrap();
+
+        // 誰も保持していないものを捨てて、マップが大きくなりすぎないようにする
+        if semaphores.len() >= IP_LIMITER_PRUNE_THRESHOLD {
+            semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        }
+
+        semaphores
+            .entry(ip)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_per_ip)))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler() -> ProbeScheduler {
+        ProbeScheduler::new(&SchedulerConfig::default())
+    }
+
+    fn failing(consecutive_failures: u32) -> PollState {
+        PollState {
+            consecutive_failures,
+            players_online: 0,
+            previous_players_online: None,
+        }
+    }
+
+    fn online(players_online: i32, previous_players_online: Option<i32>) -> PollState {
+        PollState {
+            consecutive_failures: 0,
+            players_online,
+            previous_players_online,
+        }
+    }
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn backs_off_exponentially() {
+        let scheduler = scheduler();
+        assert_eq!(scheduler.interval(&failing(1), 0.0), 600 * SECOND);
+        assert_eq!(scheduler.interval(&failing(2), 0.0), 1200 * SECOND);
+        assert_eq!(scheduler.interval(&failing(3), 0.0), 2400 * SECOND);
+        assert_eq!(scheduler.interval(&failing(4), 0.0), 3600 * SECOND);
+        assert_eq!(scheduler.interval(&failing(u32::MAX), 0.0), 3600 * SECOND);
+    }
+
+    #[test]
+    fn jitter_does_not_exceed_max_backoff() {
+        let scheduler = scheduler();
+        assert_eq!(
+            scheduler.interval(&failing(10), INTERVAL_JITTER_RATIO),
+            3600 * SECOND
+        );
+        let secs = |interval: Duration| interval.as_secs_f64().round();
+        assert_eq!(
+            secs(scheduler.interval(&failing(10), -INTERVAL_JITTER_RATIO)),
+            3240.0
+        );
+        assert_eq!(
+            secs(scheduler.interval(&failing(1), INTERVAL_JITTER_RATIO)),
+            660.0
+        );
+    }
+
+    #[test]
+    fn busy_servers_are_probed_more_often() {
+        let scheduler = scheduler();
+        assert_eq!(scheduler.interval(&online(10, Some(8)), 0.0), 600 * SECOND);
+        assert_eq!(scheduler.interval(&online(50, None), 0.0), 120 * SECOND);
+        assert_eq!(scheduler.interval(&online(15, Some(5)), 0.0), 120 * SECOND);
+        assert_eq!(scheduler.interval(&online(5, Some(15)), 0.0), 120 * SECOND);
+    }
+
+    #[test]
+    fn random_jitter_stays_in_range() {
+        let scheduler = scheduler();
+        for _ in 0..100 {
+            let interval = scheduler.next_interval(&online(0, None));
+            assert!((540 * SECOND..=660 * SECOND).contains(&interval));
+        }
+    }
+
+    #[test]
+    fn initial_probes_are_spread_within_the_interval() {
+        let scheduler = scheduler();
+        let start = Instant::now();
+        for _ in 0..100 {
+            let at = scheduler.initial_time();
+            assert!(at >= start);
+            assert!(at <= Instant::now() + 480 * SECOND);
+        }
+    }
+
+    #[test]
+    fn config_is_clamped() {
+        let scheduler = ProbeScheduler::new(&SchedulerConfig {
+            min_interval_secs: 1200,
+            max_backoff_secs: 60,
+            spread_ratio: 2.0,
+            max_concurrent_probes: 0,
+            ..Default::default()
+        });
+        assert_eq!(scheduler.min_interval, 600 * SECOND);
+        assert_eq!(scheduler.max_backoff, 600 * SECOND);
+        assert_eq!(scheduler.initial_spread, 600 * SECOND);
+        assert_eq!(scheduler.max_concurrent(), 1);
+    }
+
+    #[test]
+    fn limits_connections_per_ip() {
+        let limiter = IpLimiter::new(2);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let first = limiter.semaphore(ip).try_acquire_owned().unwrap();
+        let _second = limiter.semaphore(ip).try_acquire_owned().unwrap();
+        assert!(limiter.semaphore(ip).try_acquire_owned().is_err());
+        // 別のIPは影響を受けない
+        assert!(
+            limiter
+                .semaphore("192.0.2.2".parse().unwrap())
+                .try_acquire_owned()
+                .is_ok()
+        );
+
+        drop(first);
+        assert!(limiter.semaphore(ip).try_acquire_owned().is_ok());
+    }
+
+    #[test]
+    fn limiter_prunes_unused_semaphores() {
+        let limiter = IpLimiter::new(1);
+        let held = limiter.semaphore("192.0.2.1".parse().unwrap());
+        for index in 0..IP_LIMITER_PRUNE_THRESHOLD as u32 {
+            limiter.semaphore(IpAddr::from((0x0a00_0000 + index).to_be_bytes()));
+        }
+        // しきい値を超えたときに誰も持っていないものだけが捨てられる
+        assert_eq!(limiter.semaphores.lock().unwrap().len(), 2);
+        assert!(Arc::ptr_eq(
+            &held,
+            &limiter.semaphore("192.0.2.1".parse().unwrap())
+        ));
+    }
+
+    #[tokio::test]
+    async fn rescheduled_entries_stay_in_the_queue() {
+        // 予定を入れ直しても古い予定は残り、取り出した側が予定時刻で見分ける
+        let scheduler = scheduler();
+        let now = Instant::now();
+        let later = now + Duration::from_millis(10);
+        scheduler.schedule("a".to_string(), later);
+        scheduler.schedule("b".to_string(), later);
+        scheduler.schedule("a".to_string(), now);
+
+        assert_eq!(scheduler.next_due().await, (now, "a".to_string()));
+        assert_eq!(scheduler.next_due().await, (later, "a".to_string()));
+        assert_eq!(scheduler.next_due().await, (later, "b".to_string()));
+    }
+}
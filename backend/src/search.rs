@@ -0,0 +1,270 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{config::ServerConfig, network::Networks, text::normalize_for_search};
+
+/// 検索対象のフィールド
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Name,
+    Reading,
+    Description,
+}
+
+impl Field {
+    /// 一致したときの重み
+    fn weight(self) -> f64 {
+        match self {
+            Self::Name => 3.0,
+            Self::Reading => 2.0,
+            Self::Description => 1.0,
+        }
+    }
+}
+
+/// 検索対象の1サーバー分
+struct Document {
+    /// リスト上の項目のキー ([`Networks::listing_key`])
+    key: String,
+    /// 正規化済みのフィールド
+    fields: Vec<(Field, String)>,
+}
+
+/// ## サーバーの名前・読み・説明欄の全文検索用インデックス
+///
+/// * 日本語は単語の区切りがないので、1文字と2文字のn-gramで索引を作る
+/// * 検索語のn-gramをすべて含むサーバーを候補にし、実際に検索語を含むかどうかを確かめる
+/// * サーバーリストが更新されるたびに作り直す
+/// * ネットワークの入口は、まとめた項目の文書として扱う
+pub struct SearchIndex {
+    documents: Vec<Document>,
+    /// n-gramからそれを含む文書の番号 (昇順)
+    postings: HashMap<String, Vec<usize>>,
+}
+
+impl SearchIndex {
+    pub fn new(servers: &[ServerConfig], networks: &Networks) -> Self {
+        let documents =
+            servers
+                .iter()
+                .map(|config| {
+                    // どの言語で検索しても見つかるよう、すべての言語の文字列を入れる
+                    let mut fields = config
+                        .name
+                        .texts()
+                        .into_iter()
+                        .map(|name| (Field::Name, normalize_for_search(name)))
+                        .collect::<Vec<_>>();
+                    if let Some(reading) = &config.reading {
+                        fields.push((Field::Reading, normalize_for_search(reading)));
+                    }
+                    fields.extend(config.description.texts().into_iter().map(|description| {
+                        (Field::Description, normalize_for_search(description))
+                    }));
+                    Document {
+                        key: networks.listing_key(&config.key()).to_string(),
+                        fields,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+        let mut postings = HashMap::<String, Vec<usize>>::new();
+        for (id, document) in documents.iter().enumerate() {
+            let grams = document
+                .fields
+                .iter()
+                .flat_map(|(_, text)| ngrams(text))
+                .collect::<HashSet<_>>();
+            for gram in grams {
+                postings.entry(gram).or_default().push(id);
+            }
+        }
+
+        Self {
+            documents,
+            postings,
+        }
+    }
+
+    /// `query` に一致するサーバーのキーと関連度
+    /// 空白で区切った検索語をすべて含むものだけを返す
+    pub fn search(&self, query: &str) -> HashMap<&str, f64> {
+        let normalized = normalize_for_search(query);
+        let terms = normalized.split_whitespace().collect::<Vec<_>>();
+        if terms.is_empty() {
+            return HashMap::new();
+        }
+
+        let mut scores = HashMap::new();
+        for (index, term) in terms.iter().enumerate() {
+            let matched = self
+                .candidates(term)
+                .into_iter()
+                .filter(|id| index == 0 || scores.contains_key(id))
+                .filter_map(|id| Some((id, self.term_score(&self.documents[id], term)?)))
+                .collect::<Vec<_>>();
+
+            // 多くのサーバーに含まれる検索語ほど軽くする
+            let idf = (1.0 + self.documents.len() as f64 / matched.len().max(1) as f64).ln();
+            let mut next = HashMap::new();
+            for (id, score) in matched {
+                next.insert(id, scores.get(&id).copied().unwrap_or(0.0) + score * idf);
+            }
+            scores = next;
+        }
+
+        // 同じネットワークの入口が複数一致したときは最も高いものにする
+        let mut results = HashMap::new();
+        for (id, score) in scores {
+            let best = results
+                .entry(self.documents[id].key.as_str())
+                .or_insert(score);
+            *best = f64::max(*best, score);
+        }
+        results
+    }
+
+    /// 検索語のn-gramをすべて含む文書
+    fn candidates(&self, term: &str) -> Vec<usize> {
+        let chars = term.chars().collect::<Vec<_>>();
+        let grams = if chars.len() == 1 {
+            vec![term.to_string()]
+        } else {
+            chars
+                .windows(2)
+                .map(|pair| pair.iter().collect::<String>())
+                .collect()
+        };
+
+        let mut postings = grams
+            .iter()
+            .map(|gram| self.postings.get(gram).map(Vec::as_slice).unwrap_or(&[]))
+            .collect::<Vec<_>>();
+        postings.sort_by_key(|ids| ids.len());
+        let Some((first, rest)) = postings.split_first() else {
+            return Vec::new();
+        };
+        first
+            .iter()
+            .copied()
+            .filter(|id| rest.iter().all(|ids| ids.binary_search(id).is_ok()))
+            .collect()
+    }
+
+    /// 検索語を含むフィールドのうち最も高いスコア
+    /// 完全一致・前方一致を部分一致より高くする
+    /// どのフィールドにも含まれなければNone
+    fn term_score(&self, document: &Document, term: &str) -> Option<f64> {
+        document
+            .fields
+            .iter()
+            .filter(|(_, text)| text.contains(term))
+            .map(|(field, text)| {
+                let bonus = if text == term {
+                    2.0
+                } else if text.starts_with(term) {
+                    1.5
+                } else {
+                    1.0
+                };
+                field.weight() * bonus
+            })
+            .max_by(f64::total_cmp)
+    }
+}
+
+/// 文字列に含まれる1文字と2文字のn-gram
+/// 空白をまたぐものは含めない
+fn ngrams(text: &str) -> HashSet<String> {
+    let mut grams = HashSet::new();
+    for word in text.split_whitespace() {
+        let chars = word.chars().collect::<Vec<_>>();
+        grams.extend(chars.iter().map(char::to_string));
+        grams.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+    }
+    grams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::LocalizedText, testing::server_config};
+
+    fn server(ip: &str, name: &str, description: &str) -> ServerConfig {
+        let mut config = server_config(&format!("ip = \"{ip}\""));
+        config.name = LocalizedText::Plain(name.to_string());
+        config.description = LocalizedText::Plain(description.to_string());
+        config
+    }
+
+    fn search(servers: &[ServerConfig], query: &str) -> Vec<String> {
+        let networks = Networks::new(servers);
+        let index = SearchIndex::new(servers, &networks);
+        let mut results = index
+            .search(query)
+            .into_iter()
+            .map(|(key, score)| (key.to_string(), score))
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn finds_a_single_kanji() {
+        let servers = [
+            server("a.example.com", "まったり鯖", ""),
+            server("b.example.com", "サーバー", ""),
+        ];
+        assert_eq!(search(&servers, "鯖"), vec!["a.example.com"]);
+    }
+
+    #[test]
+    fn matches_across_kana_and_width() {
+        let servers = [
+            server("a.example.com", "さばいばる", ""),
+            server("b.example.com", "ｻﾊﾞｲﾊﾞﾙ鯖", ""),
+            server("c.example.com", "クリエイティブ", "サバイバルもある"),
+            server("d.example.com", "ミニゲーム", ""),
+        ];
+        // 名前の完全一致、前方一致、説明欄の順
+        assert_eq!(
+            search(&servers, "サバイバル"),
+            vec!["a.example.com", "b.example.com", "c.example.com"]
+        );
+    }
+
+    #[test]
+    fn ignores_case() {
+        let servers = [
+            server("a.example.com", "PvP Arena", ""),
+            server("b.example.com", "ＰＶＰ", ""),
+            server("c.example.com", "Survival", ""),
+        ];
+        assert_eq!(
+            search(&servers, "pvp"),
+            vec!["b.example.com", "a.example.com"]
+        );
+    }
+
+    #[test]
+    fn requires_every_term() {
+        let servers = [
+            server("a.example.com", "PvP サバイバル", ""),
+            server("b.example.com", "PvP", ""),
+        ];
+        assert_eq!(search(&servers, "pvp さばいばる"), vec!["a.example.com"]);
+        assert!(search(&servers, "pvp creative").is_empty());
+        assert!(search(&servers, "   ").is_empty());
+    }
+
+    #[test]
+    fn groups_network_endpoints() {
+        let mut servers = [
+            server("a.example.com", "PvP Network", ""),
+            server("b.example.com", "PvP 2", ""),
+            server("c.example.com", "PvP Solo", ""),
+        ];
+        servers[0].network = Some("pvp".to_string());
+        servers[1].network = Some("pvp".to_string());
+        assert_eq!(search(&servers, "pvp"), vec!["c.example.com", "pvp"]);
+    }
+}
@@ -2,12 +2,24 @@ use std::sync::Arc;
 
 use api::{
     serve,
-    types::{API, Ordering, Server},
+    types::{
+        API, Audience, Category, Direction, Edition, Endpoint, Health, HistoryPoint, LatencyStats,
+        Links, Motd, OfflineReason, Ordering, PlayerStats, ProbeDiagnostics, ProtocolVersion,
+        RankingScore, Resolution, SearchHit, Server, ServerDetail, ServerListPage,
+    },
 };
 use async_trait::async_trait;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::service::Service;
+use crate::{
+    config,
+    cursor::Cursor,
+    filter::ServerFilter,
+    health,
+    ordering::{self, SortKey},
+    service::{self, Service},
+    storage, version,
+};
 
 pub struct ApiServer {
     service: Arc<Service>,
@@ -27,50 +39,478 @@ impl ApiServer {
 #[async_trait]
 impl API for ApiServer {
     /// サーバーリストを取得する
-    /// 配列の順序はorderingに準拠する
+    /// 配列の順序はordering・directionに準拠する
+    /// directionを省略した場合はorderingごとの既定の向きになる
+    /// 残りの引数は絞り込みの条件で、指定したものをすべて満たすサーバーのみを返す
+    /// limitを指定すると先頭からlimit件だけを返し、続きはnext_cursorをcursorに指定して取得する
     /// 定期的に更新するならキャッシュしても問題ない
-    async fn get_server_list(&self, ordering: Ordering) -> Vec<Server> {
-        info!("Recieved get_server_list ? ordering = {:?}", ordering);
-        match ordering {
-            Ordering::Player => {
-                let players_order = self.service.online_players_order.read().unwrap().clone();
-                players_order
-                    .iter()
-                    .map(|status| Server {
-                        ip: status.ip.clone(),
-                        icon: status.icon.clone(),
-                        name: status.name.clone(),
-                        port: status.port as _,
-                        is_online: status.is_online,
-                        description: status.description.clone(),
-                        players_online: status.players_online as _,
-                        players_max: status.players_max as _,
-                        version_name: status.version_name.clone(),
-                    })
-                    .collect()
+    async fn get_server_list(
+        &self,
+        ordering: Ordering,
+        direction: Option<Direction>,
+        online_only: Option<bool>,
+        min_players: Option<i64>,
+        max_players: Option<i64>,
+        version: Option<String>,
+        protocol: Option<i64>,
+        edition: Option<Edition>,
+        tags: Option<Vec<String>>,
+        software: Option<String>,
+        category: Option<Category>,
+        language: Option<String>,
+        audience: Option<Audience>,
+        locale: Option<String>,
+        client_protocol: Option<i64>,
+        limit: Option<i64>,
+        cursor: Option<String>,
+    ) -> ServerListPage {
+        info!(
+            "Recieved get_server_list ? ordering = {:?}, direction = {:?}, online_only = {:?}, min_players = {:?}, max_players = {:?}, version = {:?}, protocol = {:?}, edition = {:?}, tags = {:?}, software = {:?}, category = {:?}, language = {:?}, audience = {:?}, locale = {:?}, client_protocol = {:?}, limit = {:?}, cursor = {:?}",
+            ordering,
+            direction,
+            online_only,
+            min_players,
+            max_players,
+            version,
+            protocol,
+            edition,
+            tags,
+            software,
+            category,
+            language,
+            audience,
+            locale,
+            client_protocol,
+            limit,
+            cursor
+        );
+
+        // カーソルは最初のページと同じ条件で取得する場合にだけ使える
+        let query = Cursor::query_hash(&(
+            (ordering, direction),
+            (
+                online_only,
+                min_players,
+                max_players,
+                &version,
+                protocol,
+                edition,
+                &tags,
+                &software,
+                category,
+                &language,
+                audience,
+            ),
+            (&locale, client_protocol),
+        ));
+
+        // カーソルがあれば最初のページと同じスナップショットの続きから返す
+        let (snapshot, offset) = match cursor {
+            Some(cursor) => {
+                let resumed = Cursor::decode(&cursor).and_then(|cursor| {
+                    if cursor.query != query {
+                        return None;
+                    }
+                    let snapshot = self.service.snapshot_at(cursor.generation)?;
+                    Some((snapshot, cursor.offset))
+                });
+                match resumed {
+                    Some(resumed) => resumed,
+                    None => {
+                        return ServerListPage {
+                            servers: Vec::new(),
+                            total: 0,
+                            generation: self.service.snapshot().generation as _,
+                            next_cursor: None,
+                            cursor_expired: true,
+                        };
+                    }
+                }
             }
-            Ordering::PlayerReverse => {
-                let players_reverse_order = self
-                    .service
-                    .online_players_reverse_order
-                    .read()
-                    .unwrap()
-                    .clone();
-                players_reverse_order
-                    .iter()
-                    .map(|status| Server {
-                        ip: status.ip.clone(),
-                        icon: status.icon.clone(),
-                        name: status.name.clone(),
-                        port: status.port as _,
-                        is_online: status.is_online,
-                        description: status.description.clone(),
-                        players_online: status.players_online as _,
-                        players_max: status.players_max as _,
-                        version_name: status.version_name.clone(),
-                    })
-                    .collect()
+            None => (self.service.snapshot(), 0),
+        };
+
+        let key = to_sort_key(ordering);
+        let direction = match direction {
+            Some(Direction::Ascending) => ordering::Direction::Ascending,
+            Some(Direction::Descending) => ordering::Direction::Descending,
+            None => key.default_direction(),
+        };
+
+        // 読み取れないバージョンを指定された場合はその条件を無視する
+        let version = version.and_then(|version| {
+            let parsed = version::parse_version(&version);
+            if parsed.is_none() {
+                info!("Ignored unparseable version filter : {:?}", version);
             }
+            parsed
+        });
+        let filter = ServerFilter {
+            online_only: online_only.unwrap_or(false),
+            min_players: min_players.map(saturate_i32),
+            max_players: max_players.map(saturate_i32),
+            version,
+            protocol: protocol.map(saturate_i32),
+            edition: edition.map(|edition| match edition {
+                Edition::Java => config::Edition::Java,
+                Edition::Bedrock => config::Edition::Bedrock,
+            }),
+            tags: tags.unwrap_or_default(),
+            software,
+            category: category.map(from_api_category),
+            language,
+            audience: audience.map(from_api_audience),
+        };
+
+        let matched = snapshot
+            .servers(key)
+            .iter(direction)
+            .filter(|status| filter.matches(status))
+            .collect::<Vec<_>>();
+        let limit = limit.map_or(usize::MAX, |limit| limit.max(1) as usize);
+        let end = offset.saturating_add(limit).min(matched.len());
+        let servers = matched
+            .get(offset..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|status| {
+                to_api_server(status, locale.as_deref(), client_protocol.map(saturate_i32))
+            })
+            .collect();
+        let next_cursor = (end < matched.len()).then(|| {
+            Cursor {
+                generation: snapshot.generation,
+                query,
+                offset: end,
+            }
+            .encode()
+        });
+
+        ServerListPage {
+            servers,
+            total: matched.len() as _,
+            generation: snapshot.generation as _,
+            next_cursor,
+            cursor_expired: false,
         }
     }
+
+    /// サーバーのプレイヤー人数とオンライン状態の推移を取得する
+    /// idはServerの値を指定する (ネットワークはget_serverと同じエントリーの推移になる)
+    /// from以上to未満の範囲を古い順に返す
+    /// 点が多すぎる場合は新しい方から5000個までを返す
+    /// 該当するサーバーがなければ空の配列を返す
+    async fn get_server_history(
+        &self,
+        id: String,
+        from: i64,
+        to: i64,
+        resolution: Resolution,
+    ) -> Vec<HistoryPoint> {
+        info!(
+            "Recieved get_server_history ? id = {}, from = {}, to = {}, resolution = {:?}",
+            id, from, to, resolution
+        );
+        let resolution = match resolution {
+            Resolution::Raw => storage::Resolution::Raw,
+            Resolution::Hour => storage::Resolution::Hour,
+            Resolution::Day => storage::Resolution::Day,
+        };
+
+        match self.service.history(&id, from, to, resolution).await {
+            Ok(points) => points.into_iter().map(to_api_history_point).collect(),
+            Err(error) => {
+                warn!("Failed to get history : {:#}", error);
+                Vec::new()
+            }
+        }
+    }
+
+    /// サーバーの名前・読み・説明欄を検索する
+    /// 関連度の高い順に返し、同じ関連度ならorderingの既定の向きの順にする
+    async fn search_servers(
+        &self,
+        query: String,
+        ordering: Ordering,
+        limit: Option<i64>,
+        locale: Option<String>,
+    ) -> Vec<SearchHit> {
+        info!(
+            "Recieved search_servers ? query = {:?}, ordering = {:?}, limit = {:?}, locale = {:?}",
+            query, ordering, limit, locale
+        );
+        let key = to_sort_key(ordering);
+        let index = self.service.search_index();
+        let scores = index.search(&query);
+
+        let snapshot = self.service.snapshot();
+        let mut hits = snapshot
+            .servers(key)
+            .iter(key.default_direction())
+            .filter_map(|status| Some((status, *scores.get(status.key.as_str())?)))
+            .collect::<Vec<_>>();
+        // 安定ソートなので同じ関連度ならリストの順序のまま
+        hits.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let limit = limit.map_or(usize::MAX, |limit| limit.max(1) as usize);
+        hits.into_iter()
+            .take(limit)
+            .map(|(status, score)| SearchHit {
+                server: to_api_server(status, locale.as_deref(), None),
+                score,
+            })
+            .collect()
+    }
+
+    /// IDを指定してサーバーの詳細を取得する
+    /// 該当するサーバーがなければnull
+    async fn get_server(&self, id: String, locale: Option<String>) -> Option<ServerDetail> {
+        info!("Recieved get_server ? id = {}, locale = {:?}", id, locale);
+
+        let detail = match self.service.server_detail(&id).await {
+            Ok(detail) => detail?,
+            Err(error) => {
+                warn!("Failed to get server detail : {:#}", error);
+                return None;
+            }
+        };
+        Some(ServerDetail {
+            server: to_api_server(&detail.status, locale.as_deref(), None),
+            motd: detail.motd.map(|motd| Motd {
+                plain: motd.plain,
+                legacy: motd.legacy,
+                json: motd.json,
+            }),
+            latency_stats: detail.latency.map(|latency| LatencyStats {
+                min: latency.min,
+                average: latency.average,
+                max: latency.max,
+                samples: latency.samples,
+            }),
+            probe: ProbeDiagnostics {
+                last_probe_at: detail.probe.last_probe_at,
+                next_probe_at: detail.probe.next_probe_at,
+                consecutive_failures: detail.probe.consecutive_failures as _,
+                last_error: detail.probe.last_error,
+                resolved_address: detail.probe.resolved_address,
+                connect_ms: detail.probe.connect_ms,
+                accepts_transfers: detail.probe.accepts_transfers,
+                transfer_checked_at: detail.probe.transfer_checked_at,
+            },
+            history: detail
+                .history
+                .into_iter()
+                .map(to_api_history_point)
+                .collect(),
+        })
+    }
+
+    /// プロトコル番号とリリース名の対応表を古い順に取得する
+    async fn get_protocol_versions(&self) -> Vec<ProtocolVersion> {
+        info!("Recieved get_protocol_versions");
+
+        version::RELEASES
+            .iter()
+            .map(|release| ProtocolVersion {
+                protocol: release.protocol as _,
+                name: release.name(),
+            })
+            .collect()
+    }
+}
+
+/// APIのOrderingに対応する並び順
+fn to_sort_key(ordering: Ordering) -> SortKey {
+    match ordering {
+        Ordering::Player => SortKey::Players,
+        Ordering::Uptime => SortKey::Uptime,
+        Ordering::WeeklyAveragePlayers => SortKey::WeeklyAveragePlayers,
+        Ordering::Trending => SortKey::Trending,
+        Ordering::Ranked => SortKey::Ranked,
+        Ordering::Shuffle => SortKey::Shuffle,
+        Ordering::Name => SortKey::Name,
+        Ordering::Latency => SortKey::Latency,
+        Ordering::Version => SortKey::Version,
+        Ordering::RecentlyAdded => SortKey::RecentlyAdded,
+    }
+}
+
+/// APIのCategoryに対応するジャンル
+fn from_api_category(category: Category) -> config::Category {
+    match category {
+        Category::Survival => config::Category::Survival,
+        Category::Minigames => config::Category::Minigames,
+        Category::Pvp => config::Category::Pvp,
+        Category::Rpg => config::Category::Rpg,
+        Category::Creative => config::Category::Creative,
+        Category::Modded => config::Category::Modded,
+    }
+}
+
+/// APIのAudienceに対応する年齢層
+fn from_api_audience(audience: Audience) -> config::Audience {
+    match audience {
+        Audience::Everyone => config::Audience::Everyone,
+        Audience::Teen => config::Audience::Teen,
+        Audience::Adult => config::Audience::Adult,
+    }
+}
+
+/// APIのServer型に変換する
+/// 名前と説明欄は `locale` の言語のものにする
+/// `client_protocol` があれば、そのクライアントが参加できるかを含める
+fn to_api_server(
+    status: &service::MinecraftServerStatus,
+    locale: Option<&str>,
+    client_protocol: Option<i32>,
+) -> Server {
+    let is_compatible = |is_online: bool, version_name: &str, version_protocol: i32| {
+        client_protocol.map(|client_protocol| {
+            let (min, max) = version::protocol_range(version_name, version_protocol);
+            is_online && (min..=max).contains(&client_protocol)
+        })
+    };
+
+    Server {
+        id: status.id.clone(),
+        ip: status.ip.clone(),
+        icon: status.icon.clone(),
+        name: status.name.get(locale).to_string(),
+        port: status.port as _,
+        is_online: status.is_online,
+        health: to_api_health(status.health),
+        description: status.description.get(locale).to_string(),
+        players_online: status.players_online as _,
+        players_max: status.players_max as _,
+        version_name: status.version_name.clone(),
+        offline_reason: status.offline_reason.map(|reason| match reason {
+            service::OfflineReason::Unreachable => OfflineReason::Unreachable,
+            service::OfflineReason::Blocked => OfflineReason::Blocked,
+        }),
+        uptime_24h: status.uptime.uptime_24h,
+        uptime_7d: status.uptime.uptime_7d,
+        uptime_30d: status.uptime.uptime_30d,
+        last_seen_online: status.uptime.last_seen_online,
+        longest_outage: status.uptime.longest_outage_secs,
+        mtbf: status.uptime.mtbf_secs,
+        latency: status.latency_ms,
+        added_at: status.added_at,
+        players_daily: status.players.daily.map(to_api_player_stats),
+        players_weekly: status.players.weekly.map(to_api_player_stats),
+        trending_score: status.trending_score,
+        ranking: RankingScore {
+            total: status.ranking.total,
+            players: status.ranking.players,
+            average_players: status.ranking.average_players,
+            uptime: status.ranking.uptime,
+            latency: status.ranking.latency,
+            boost: status.ranking.boost,
+        },
+        tags: status.tags.clone(),
+        editions: status
+            .editions
+            .iter()
+            .copied()
+            .map(to_api_edition)
+            .collect(),
+        software: status.software.map(str::to_string),
+        categories: status
+            .categories
+            .iter()
+            .map(|category| match category {
+                config::Category::Survival => Category::Survival,
+                config::Category::Minigames => Category::Minigames,
+                config::Category::Pvp => Category::Pvp,
+                config::Category::Rpg => Category::Rpg,
+                config::Category::Creative => Category::Creative,
+                config::Category::Modded => Category::Modded,
+            })
+            .collect(),
+        languages: status.languages.clone(),
+        audience: status.audience.map(|audience| match audience {
+            config::Audience::Everyone => Audience::Everyone,
+            config::Audience::Teen => Audience::Teen,
+            config::Audience::Adult => Audience::Adult,
+        }),
+        links: Links {
+            website: status.links.website.clone(),
+            discord: status.links.discord.clone(),
+            x: status.links.x.clone(),
+        },
+        endpoints: status
+            .endpoints
+            .iter()
+            .map(|endpoint| Endpoint {
+                name: endpoint.name.get(locale).to_string(),
+                ip: endpoint.ip.clone(),
+                port: endpoint.port as _,
+                is_online: endpoint.health.is_online(),
+                health: to_api_health(endpoint.health),
+                players_online: endpoint.players_online as _,
+                players_max: endpoint.players_max as _,
+                latency: endpoint.latency_ms,
+                version_name: endpoint.version_name.clone(),
+                editions: endpoint
+                    .editions
+                    .iter()
+                    .copied()
+                    .map(to_api_edition)
+                    .collect(),
+                is_best: endpoint.is_best,
+                transferable: endpoint.transferable,
+                compatible: is_compatible(
+                    endpoint.health.is_online(),
+                    &endpoint.version_name,
+                    endpoint.version_protocol,
+                ),
+            })
+            .collect(),
+        transferable: status.transferable,
+        compatible: is_compatible(
+            status.is_online,
+            &status.version_name,
+            status.version_protocol,
+        ),
+    }
+}
+
+fn to_api_health(health: health::Health) -> Health {
+    match health {
+        health::Health::Online => Health::Online,
+        health::Health::Suspect => Health::Suspect,
+        health::Health::Offline => Health::Offline,
+        health::Health::Maintenance => Health::Maintenance,
+    }
+}
+
+fn to_api_edition(edition: config::Edition) -> Edition {
+    match edition {
+        config::Edition::Java => Edition::Java,
+        config::Edition::Bedrock => Edition::Bedrock,
+    }
+}
+
+/// APIの整数をi32の範囲に丸める
+fn saturate_i32(value: i64) -> i32 {
+    value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// APIのHistoryPoint型に変換する
+fn to_api_history_point(point: storage::HistoryPoint) -> HistoryPoint {
+    HistoryPoint {
+        timestamp: point.timestamp,
+        players_online: point.players_avg,
+        players_peak: point.players_peak as _,
+        online_ratio: point.online_ratio,
+        is_online: point.online_ratio > 0.0,
+    }
+}
+
+/// APIのPlayerStats型に変換する
+fn to_api_player_stats(summary: storage::PlayerSummary) -> PlayerStats {
+    PlayerStats {
+        average: summary.average,
+        peak: summary.peak as _,
+        median: summary.median,
+    }
 }
@@ -1,164 +1,1303 @@
 use std::{
-    sync::{Arc, RwLock},
-    time::Duration,
+    collections::{HashMap, VecDeque},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use futures::future::join_all;
-use tokio::{fs::File, io::AsyncReadExt, time::sleep};
-use tracing::{info, warn};
+use futures::{StreamExt, stream::FuturesUnordered};
+use tokio::{
+    sync::Notify,
+    task::spawn_blocking,
+    time::{Instant, sleep},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    address_policy::{AddressPolicy, BlockedAddressError},
+    config::{
+        Audience, BackendConfig, Category, Edition, Links, LocalizedText, ServerConfig,
+        ServersConfig,
+    },
+    health::{Health, HealthPolicy},
+    minecraft::{MinecraftServerInfo, Prober},
+    network::{EndpointStatus, Networks},
+    ordering::{Direction, SortKey, SortedStatuses, Sorter},
+    ranking::{RankingPolicy, RankingScore, Rotation},
+    registry::RegistryFetcher,
+    scheduler::{IpLimiter, PollState, ProbeScheduler},
+    search::SearchIndex,
+    stats::{PlayerStats, UptimeStats, server_stats},
+    storage::{
+        HistoryPoint, HistoryStore, LatencySummary, ProbeRecord, Resolution, RetentionPolicy,
+        SqliteHistoryStore, unix_now,
+    },
+    text::strip_formatting,
+    validate::validate,
+    version::{self, TRANSFER_MIN_PROTOCOL, detect_software},
+};
+
+/// 公開中のステータスを再計算する最短間隔
+/// pingの結果が続けて届いたときにソートをまとめて行うため
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 履歴を集計・削除し、プレイヤー人数を集計する間隔
+const COMPACT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 稼働状況などの統計を計算し直す間隔
+const STATS_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 稼働状況などの統計を1回にまとめて計算するサーバー数
+const STATS_BATCH: usize = 200;
 
-use crate::{config::ServersConfig, minecraft::MinecraftServerInfo};
+/// サーバーの詳細で往復時間を集計する期間
+const DETAIL_LATENCY_WINDOW: i64 = 24 * 60 * 60;
+
+/// サーバーの詳細で返す推移の期間
+const DETAIL_HISTORY_WINDOW: i64 = 7 * 24 * 60 * 60;
+
+/// スナップショットを保持しておく期間
+/// ページングの途中で作り直されても、この期間内ならカーソルが指す古いスナップショットから続きを返せる
+const SNAPSHOT_RETENTION: Duration = Duration::from_secs(5 * 60);
+
+/// 保持しておくスナップショットの数の上限
+/// 更新が続いても、保持期間内のスナップショットがこれより多くは残らないようにする
+const RETAINED_SNAPSHOTS: usize = 16;
 
 /// ## Minecraftステータスチェッカーサービス
 ///
-/// * 中身をRwLock<Arc<Vec<MinecraftServerStatus>>>で持っているのはクローンのコストを削減するため
+/// * pingの結果は届いた順にサーバーごとの状態マップへ反映する
+/// * 公開用の並び順はその状態マップから作り直し、スナップショットとしてまとめて差し替える
+/// * ページングのため、直近のスナップショットをいくつか残しておく
+/// * スナップショットをArc<Snapshot>で持っているのはクローンのコストを削減するため
+/// * 変わっていないサーバーのステータスは直前のスナップショットとArcを共有し、作り直さない
 /// * クローンコストを浮かせることでRwLockの保持が最短になり、並列度が高くなる
 pub struct Service {
-    pub online_players_order: RwLock<Arc<Vec<MinecraftServerStatus>>>,
-    pub online_players_reverse_order: RwLock<Arc<Vec<MinecraftServerStatus>>>,
+    registry: RegistryFetcher,
+    prober: Prober,
+    scheduler: ProbeScheduler,
+    health_policy: HealthPolicy,
+    /// 転送を受け付けるかを確かめ直す間隔 (確かめないならNone)
+    transfer_check_interval: Option<Duration>,
+    ranking: RankingPolicy,
+    rotation: Rotation,
+    sorter: Sorter,
+    history: Arc<dyn HistoryStore>,
+    /// サーバーごとの最新状態 (キーは [`ServerConfig::key`])
+    states: RwLock<HashMap<String, ServerState>>,
+    /// 直近のスナップショット (末尾が最新)
+    snapshots: RwLock<VecDeque<Arc<Snapshot>>>,
+    generation: AtomicU64,
+    /// 状態マップのエントリーが変わるたびに割り当てる番号 ([`ServerState::revision`])
+    revision: AtomicU64,
+    /// 直前に公開したステータス (キーは [`Networks::listing_key`])
+    published: Mutex<HashMap<String, PublishedStatus>>,
+    /// servers.tomlのネットワークの構成
+    networks: RwLock<Arc<Networks>>,
+    /// サーバーリストの全文検索用インデックス
+    search: RwLock<Arc<SearchIndex>>,
+    /// 状態マップが更新されたことを公開タスクに知らせる
+    dirty: Notify,
+}
+
+/// ある時点のサーバーリスト
+/// 各並び順はステータスをArcで共有する
+pub struct Snapshot {
+    /// 作り直すたびに増える番号 (起動時刻のUNIX時間 (ミリ秒) から始まる)
+    pub generation: u64,
+    /// 公開した時刻
+    published_at: Instant,
+    orders: HashMap<SortKey, SortedStatuses>,
+}
+
+impl Snapshot {
+    /// `key` で並べたサーバーリスト
+    pub fn servers(&self, key: SortKey) -> &SortedStatuses {
+        &self.orders[&key]
+    }
+}
+
+/// 保持する期間を過ぎたスナップショットと、上限の数を超えた古いスナップショットを捨てる
+/// 最新のものは常に残す
+fn prune_snapshots(snapshots: &mut VecDeque<Arc<Snapshot>>, now: Instant) {
+    while snapshots.len() > RETAINED_SNAPSHOTS
+        || snapshots.len() > 1
+            && snapshots
+                .front()
+                .is_some_and(|oldest| now.duration_since(oldest.published_at) > SNAPSHOT_RETENTION)
+    {
+        snapshots.pop_front();
+    }
+}
+
+/// 公開したステータスと、その元になったエントリーの番号
+struct PublishedStatus {
+    /// エントリーのキーと [`ServerState::revision`] (servers.tomlの順)
+    revisions: Vec<(String, u64)>,
+    status: Arc<MinecraftServerStatus>,
+}
+
+struct ServerState {
+    config: ServerConfig,
+    /// 内容が変わるたびに新しく割り当てる番号
+    /// 公開するステータスを作り直すかはこれで判断する
+    revision: u64,
+    /// 一度もpingしていなければNone
+    health: Option<Health>,
+    /// 最後に成功したpingの結果
+    last_info: Option<MinecraftServerInfo>,
+    /// 最後に失敗したpingの理由
+    offline_reason: Option<OfflineReason>,
+    /// 連続で失敗した回数
+    consecutive_failures: u32,
+    /// 次回pingの予定時刻
+    /// スケジューラーのキューにこれと異なる時刻で残っている予定は古いものとして捨てる
+    next_probe: Instant,
+    /// 履歴から計算した稼働状況
+    uptime: UptimeStats,
+    /// 集計済みのプレイヤー人数
+    players: PlayerStats,
+    /// 直近の人数の伸び ([`crate::stats::trending_score`])
+    trending_score: Option<f64>,
+    /// servers.tomlに初めて載った時刻 (UNIX時間, 秒)
+    added_at: Option<i64>,
+    /// 最後にpingした時刻 (UNIX時間, 秒)
+    last_probe_at: Option<i64>,
+    /// 最後に失敗したpingのエラーメッセージ (成功すると消える)
+    last_error: Option<String>,
+    /// 最後に転送を受け付けるかを確かめた結果
+    transfer_check: Option<TransferCheck>,
+}
+
+/// 転送を受け付けるかを実際に確かめた結果
+#[derive(Debug, Clone, Copy)]
+struct TransferCheck {
+    /// 判断できなければNone
+    accepted: Option<bool>,
+    /// 確かめた時刻 (UNIX時間, 秒)
+    checked_at: i64,
+}
+
+/// サーバーの詳細
+pub struct ServerDetail {
+    pub status: Arc<MinecraftServerStatus>,
+    /// 最後に成功したpingのMOTD
+    pub motd: Option<Motd>,
+    pub probe: ProbeDiagnostics,
+    /// 直近24時間のpingの往復時間
+    pub latency: Option<LatencySummary>,
+    /// 直近7日間の1日ごとの推移
+    pub history: Vec<HistoryPoint>,
+}
+
+/// pingで返されたMOTD
+pub struct Motd {
+    /// 書式を取り除いたもの
+    pub plain: String,
+    /// 書式を書式コード (`§`) で表したもの
+    pub legacy: String,
+    /// サーバーが返したJSONそのまま
+    pub json: String,
+}
+
+/// pingの状況
+pub struct ProbeDiagnostics {
+    /// 最後にpingした時刻 (UNIX時間, 秒)
+    pub last_probe_at: Option<i64>,
+    /// 次にpingする予定の時刻 (UNIX時間, 秒)
+    pub next_probe_at: i64,
+    /// 連続で失敗した回数
+    pub consecutive_failures: u32,
+    /// 最後に失敗したpingのエラーメッセージ
+    pub last_error: Option<String>,
+    /// 最後に成功したpingの接続先
+    pub resolved_address: Option<String>,
+    /// 最後に成功したpingの接続にかかった時間 (ミリ秒)
+    pub connect_ms: Option<i64>,
+    /// 最後に転送を受け付けるかを確かめた結果 (確かめていないか、判断できなければNone)
+    pub accepts_transfers: Option<bool>,
+    /// 最後に転送を受け付けるかを確かめた時刻 (UNIX時間, 秒)
+    pub transfer_checked_at: Option<i64>,
+}
+
+enum ProbeOutcome {
+    /// pingの結果と、今回転送を受け付けるかを確かめたならその結果
+    Online(MinecraftServerInfo, Option<TransferCheck>),
+    /// 失敗の理由とエラーメッセージ
+    Offline(OfflineReason, String),
 }
 
 impl Service {
-    pub fn new() -> Self {
-        Self {
-            online_players_order: RwLock::new(Arc::new(Vec::new())),
-            online_players_reverse_order: RwLock::new(Arc::new(Vec::new())),
-        }
+    pub fn new(config: &BackendConfig) -> anyhow::Result<Self> {
+        let sorter = Sorter::new()?;
+        // 再起動の前に発行したカーソルが別のスナップショットを指さないよう、番号は起動時刻から数える
+        let epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0);
+        Ok(Self {
+            registry: RegistryFetcher::new(&config.registry)?,
+            prober: Prober::new(
+                AddressPolicy::new(&config.address_policy)?,
+                IpLimiter::new(config.scheduler.max_connections_per_ip),
+            )?,
+            scheduler: ProbeScheduler::new(&config.scheduler),
+            health_policy: HealthPolicy::new(&config.health),
+            transfer_check_interval: config
+                .transfer
+                .check
+                .then(|| Duration::from_secs(config.transfer.check_interval_secs)),
+            ranking: RankingPolicy::new(&config.ranking),
+            rotation: Rotation::new(&config.ranking),
+            history: Arc::new(SqliteHistoryStore::open(
+                &config.history.path,
+                RetentionPolicy::new(&config.history),
+            )?),
+            states: RwLock::new(HashMap::new()),
+            snapshots: RwLock::new(VecDeque::from([Arc::new(Snapshot {
+                generation: epoch,
+                published_at: Instant::now(),
+                orders: SortKey::ALL
+                    .into_iter()
+                    .map(|key| (key, sorter.sort(&[], key)))
+                    .collect(),
+            })])),
+            sorter,
+            generation: AtomicU64::new(epoch),
+            revision: AtomicU64::new(0),
+            published: Mutex::new(HashMap::new()),
+            networks: RwLock::new(Arc::new(Networks::default())),
+            search: RwLock::new(Arc::new(SearchIndex::new(&[], &Networks::default()))),
+            dirty: Notify::new(),
+        })
     }
 
-    pub async fn start(&self) {
-        loop {
-            info!("Getting server status...");
+    /// 公開中のスナップショットを取得する
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshots.read().unwrap().back().unwrap().clone()
+    }
 
-            async fn download() -> Result<(), String> {
-                let url = "https://raw.githubusercontent.com/Japan-Minecraft-Server-List/Minecraft-Servers-Registry/refs/heads/main/servers.toml";
-                let out = "servers.toml";
+    /// 番号が `generation` のスナップショットを取得する
+    /// 既に破棄されていればNone
+    pub fn snapshot_at(&self, generation: u64) -> Option<Arc<Snapshot>> {
+        self.snapshots
+            .read()
+            .unwrap()
+            .iter()
+            .find(|snapshot| snapshot.generation == generation)
+            .cloned()
+    }
 
-                let bytes = reqwest::get(url)
-                    .await
-                    .map_err(|error| error.to_string())?
-                    .error_for_status()
-                    .map_err(|error| error.to_string())?
-                    .bytes()
-                    .await
-                    .map_err(|error| error.to_string())?;
-                tokio::fs::write(out, bytes)
-                    .await
-                    .map_err(|error| error.to_string())?;
+    /// リスト上の項目の状態と履歴を代表するエントリーのキー
+    /// ネットワークはservers.tomlで最初に書かれたエントリーのものを見せる (入口ごとの状態はendpointsにある)
+    fn primary_key(&self, status: &MinecraftServerStatus) -> String {
+        let networks = self.networks.read().unwrap();
+        networks
+            .members(&status.key)
+            .and_then(|members| members.first())
+            .unwrap_or(&status.key)
+            .clone()
+    }
 
-                Ok(())
-            }
+    /// IDが `id` のサーバーの詳細を取得する
+    /// まだ一度もpingしていないサーバーや、該当するサーバーがなければNone
+    pub async fn server_detail(&self, id: &str) -> anyhow::Result<Option<ServerDetail>> {
+        let Some(status) = self.find_status(id) else {
+            return Ok(None);
+        };
+
+        let key = self.primary_key(&status);
+        let (motd, probe) = {
+            let states = self.states.read().unwrap();
+            let Some(state) = states.get(&key) else {
+                return Ok(None);
+            };
+            let info = state.last_info.as_ref();
+            let motd = info.map(|info| Motd {
+                plain: strip_formatting(&info.motd),
+                legacy: info.motd_legacy.clone(),
+                json: info.motd_json.clone(),
+            });
+            let until_next_probe = state.next_probe.saturating_duration_since(Instant::now());
+            let probe = ProbeDiagnostics {
+                last_probe_at: state.last_probe_at,
+                next_probe_at: unix_now() + until_next_probe.as_secs() as i64,
+                consecutive_failures: state.consecutive_failures,
+                last_error: state.last_error.clone(),
+                resolved_address: info.map(|info| info.resolved.to_string()),
+                connect_ms: info.map(|info| info.connect_ms as _),
+                accepts_transfers: state.transfer_check.and_then(|check| check.accepted),
+                transfer_checked_at: state.transfer_check.map(|check| check.checked_at),
+            };
+            (motd, probe)
+        };
 
-            if let Err(error) = download().await {
-                warn!("Faileed to download servers.toml : {}", error.to_string());
-                warn!("Retry in 60 seconds...");
+        let history = self.history.clone();
+        let (latency, history) = spawn_blocking(move || -> anyhow::Result<_> {
+            let now = unix_now();
+            let latency = history.latency_summary(&key, now - DETAIL_LATENCY_WINDOW)?;
+            let history =
+                history.history(&key, now - DETAIL_HISTORY_WINDOW, now + 1, Resolution::Day)?;
+            Ok((latency, history))
+        })
+        .await??;
 
-                sleep(Duration::from_secs(60)).await;
-                continue;
+        Ok(Some(ServerDetail {
+            status,
+            motd,
+            probe,
+            latency,
+            history,
+        }))
+    }
+
+    /// 現在のサーバーリストの検索用インデックスを取得する
+    pub fn search_index(&self) -> Arc<SearchIndex> {
+        self.search.read().unwrap().clone()
+    }
+
+    /// IDが `id` のリスト上の項目を公開中のスナップショットから探す
+    fn find_status(&self, id: &str) -> Option<Arc<MinecraftServerStatus>> {
+        self.snapshot()
+            .servers(SortKey::Players)
+            .iter(Direction::Descending)
+            .find(|status| status.id == id)
+            .cloned()
+    }
+
+    /// IDが `id` のサーバーの履歴を取得する
+    /// ネットワークは詳細と同じく代表するエントリーの履歴を返す
+    /// 該当するサーバーがなければ空を返す
+    pub async fn history(
+        &self,
+        id: &str,
+        from: i64,
+        to: i64,
+        resolution: Resolution,
+    ) -> anyhow::Result<Vec<HistoryPoint>> {
+        let Some(status) = self.find_status(id) else {
+            return Ok(Vec::new());
+        };
+        let key = self.primary_key(&status);
+        let history = self.history.clone();
+        spawn_blocking(move || history.history(&key, from, to, resolution)).await?
+    }
+
+    /// servers.tomlを取得・検証する
+    /// `cached` なら手元に保存したものを使う
+    /// 署名やTOMLが不正な場合はエラーを返し、呼び出し側は前回のものを使い続ける
+    async fn load_registry(&self, cached: bool) -> anyhow::Result<ServersConfig> {
+        let source = if cached {
+            self.registry.load_cached().await?
+        } else {
+            self.registry.fetch().await?
+        };
+
+        // サーバーリストのTOMLファイルとしてパースし、各エントリーを検証する
+        let validated =
+            validate(source.as_str()).map_err(|error| anyhow::anyhow!("servers.toml:{error}"))?;
+        for diagnostic in validated.diagnostics.iter() {
+            warn!("servers.toml:{}", diagnostic);
+        }
+        if validated.rejected > 0 {
+            warn!(
+                "{} entries in servers.toml are rejected",
+                validated.rejected
+            );
+        }
+
+        Ok(validated.config)
+    }
+
+    /// サーバーリストに載っているサーバーの初出時刻を取得する
+    /// 初めて見たサーバーは現在時刻で登録する
+    async fn register_servers(&self, servers_config: &ServersConfig) -> HashMap<String, i64> {
+        let keys = servers_config
+            .servers
+            .iter()
+            .map(ServerConfig::key)
+            .collect::<Vec<_>>();
+        let history = self.history.clone();
+        match spawn_blocking(move || history.register_servers(&keys, unix_now())).await {
+            Ok(Ok(added_at)) => added_at,
+            Ok(Err(error)) => {
+                warn!("Failed to register servers : {:#}", error);
+                HashMap::new()
+            }
+            Err(error) => {
+                warn!("Failed to register servers : {}", error);
+                HashMap::new()
             }
+        }
+    }
 
-            // ファイルのopenを試みる
-            let Ok(mut file) = File::open("servers.toml").await else {
-                warn!("Faileed to open servers.toml");
-                warn!("Retry in 60 seconds...");
+    /// 状態マップをサーバーリストに合わせる
+    /// 既存のサーバーは直前のpingの結果と予定を引き継ぎ、新しいサーバーはすぐにpingする
+    /// ただし起動直後は一斉にpingしないよう予定を散らす
+    /// ネットワークの構成と検索用インデックスもここで作り直す
+    fn sync_registry(
+        &self,
+        servers_config: &ServersConfig,
+        added_at: &HashMap<String, i64>,
+        initial: bool,
+    ) {
+        let mut states = self.states.write().unwrap();
+        let mut previous = std::mem::take(&mut *states);
 
-                sleep(Duration::from_secs(60)).await;
-                continue;
+        for config in servers_config.servers.iter() {
+            let key = config.key();
+            // servers.tomlに追加日があればそれを優先する
+            let added_at = config
+                .added_at_unix()
+                .or_else(|| added_at.get(&key).copied());
+            let state = match previous.remove(&key) {
+                Some(state) => ServerState {
+                    config: config.clone(),
+                    revision: self.next_revision(),
+                    added_at: added_at.or(state.added_at),
+                    ..state
+                },
+                None => {
+                    let next_probe = if initial {
+                        self.scheduler.initial_time()
+                    } else {
+                        Instant::now()
+                    };
+                    self.scheduler.schedule(key.clone(), next_probe);
+                    ServerState {
+                        config: config.clone(),
+                        revision: self.next_revision(),
+                        health: None,
+                        last_info: None,
+                        offline_reason: None,
+                        consecutive_failures: 0,
+                        next_probe,
+                        uptime: UptimeStats::default(),
+                        players: PlayerStats::default(),
+                        trending_score: None,
+                        added_at,
+                        last_probe_at: None,
+                        last_error: None,
+                        transfer_check: None,
+                    }
+                }
             };
+            states.insert(key, state);
+        }
+        drop(states);
 
-            // UTF-8読みを試みる
-            let mut source = String::new();
-            if let Err(_) = file.read_to_string(&mut source).await {
-                warn!("Faileed to read as utf-8 servers.toml");
-                warn!("Retry in 60 seconds...");
+        let networks = Networks::new(&servers_config.servers);
+        *self.search.write().unwrap() =
+            Arc::new(SearchIndex::new(&servers_config.servers, &networks));
+        *self.networks.write().unwrap() = Arc::new(networks);
 
-                sleep(Duration::from_secs(60)).await;
-                continue;
-            };
+        self.dirty.notify_one();
+    }
 
-            // サーバーリストのTOMLファイルとしてパースする
-            let servers_config = match ServersConfig::from_str(source.as_str()) {
-                Ok(config) => config,
-                Err(error) => {
-                    warn!("Faileed to read servers.toml : {}", error);
-                    warn!("Retry in 60 seconds...");
+    /// 状態マップのエントリーに割り当てる新しい番号
+    fn next_revision(&self) -> u64 {
+        self.revision.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// pingの結果を状態マップに反映し、次回のpingの予定を入れる
+    fn apply_probe(&self, key: &str, outcome: ProbeOutcome) {
+        let mut states = self.states.write().unwrap();
+        // ping中にサーバーリストから消えたものは捨てる
+        let Some(state) = states.get_mut(key) else {
+            return;
+        };
 
-                    sleep(Duration::from_secs(60)).await;
+        let previous_players_online = state.last_info.as_ref().map(|info| info.players_online);
+        state.revision = self.next_revision();
+        state.last_probe_at = Some(unix_now());
+        let interval = match outcome {
+            ProbeOutcome::Online(info, transfer_check) => {
+                let interval = self.scheduler.next_interval(&PollState {
+                    consecutive_failures: 0,
+                    players_online: info.players_online,
+                    previous_players_online,
+                });
+                let health = self.health_policy.on_success(&info);
+                match (state.health, health) {
+                    (previous, Health::Maintenance) if previous != Some(Health::Maintenance) => {
+                        info!("{} is under maintenance", key)
+                    }
+                    (Some(Health::Maintenance), Health::Online) => {
+                        info!("{} is back from maintenance", key)
+                    }
+                    _ => {}
+                }
+                state.health = Some(health);
+                state.consecutive_failures = 0;
+                state.offline_reason = None;
+                state.last_error = None;
+                state.last_info = Some(info);
+                if transfer_check.is_some() {
+                    state.transfer_check = transfer_check;
+                }
+                interval
+            }
+            ProbeOutcome::Offline(reason, error) => {
+                state.consecutive_failures += 1;
+                state.last_error = Some(error);
+                let health =
+                    self.health_policy
+                        .on_failure(state.health, state.consecutive_failures, reason);
+                match (state.health, health) {
+                    (Some(Health::Online), Health::Suspect) => {
+                        info!("{} is suspected to be offline ({:?})", key, reason)
+                    }
+                    (
+                        Some(Health::Online | Health::Suspect | Health::Maintenance),
+                        Health::Offline,
+                    ) => {
+                        info!("{} is offline ({:?})", key, reason)
+                    }
+                    _ => {}
+                }
+                state.health = Some(health);
+                state.offline_reason = Some(reason);
+
+                match health {
+                    // 短い間隔で再確認する
+                    Health::Suspect | Health::Maintenance => self.health_policy.retry_delay(),
+                    _ => self.scheduler.next_interval(&PollState {
+                        consecutive_failures: self
+                            .health_policy
+                            .failures_since_offline(state.consecutive_failures),
+                        players_online: 0,
+                        previous_players_online,
+                    }),
+                }
+            }
+        };
+
+        debug!("Next probe of {} in {:?}", key, interval);
+        state.next_probe = Instant::now() + interval;
+        let next_probe = state.next_probe;
+        drop(states);
+
+        self.scheduler.schedule(key.to_string(), next_probe);
+        self.dirty.notify_one();
+    }
+
+    /// 状態マップから並び順を作り直して公開する
+    /// 各並び順で同順位のものは、ローテーションの区間ごとに決まる順に並ぶ
+    /// 同じネットワークのサーバーは1つにまとめてから順位を付ける
+    /// 前回から変わっていない項目は前回のステータスをそのまま使う
+    fn publish(&self) {
+        let seed = self.rotation.seed(unix_now());
+        let networks = self.networks.read().unwrap().clone();
+        let mut published = self.published.lock().unwrap();
+        let mut previous = std::mem::take(&mut *published);
+        let statuses = {
+            let states = self.states.read().unwrap();
+            let mut keys = states
+                .iter()
+                .filter(|(_, state)| state.health.is_some())
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>();
+            keys.sort_by_cached_key(|key| self.rotation.key(seed, key));
+
+            // ネットワークは最初に現れたエントリーの位置に置く
+            let mut statuses = Vec::new();
+            for key in keys {
+                let listing = networks.listing_key(key);
+                if published.contains_key(listing) {
                     continue;
                 }
+                let single;
+                let members = match networks.members(listing) {
+                    Some(members) => members,
+                    None => {
+                        single = [key.to_string()];
+                        &single
+                    }
+                };
+                let revisions = members
+                    .iter()
+                    .filter_map(|key| Some((key.clone(), states.get(key)?.revision)))
+                    .collect::<Vec<_>>();
+                let status = match previous.remove(listing) {
+                    Some(entry) if entry.revisions == revisions => entry.status,
+                    _ => {
+                        let mut statuses = members
+                            .iter()
+                            .filter_map(|key| Some((key, states.get(key)?)))
+                            .filter(|(_, state)| state.health.is_some())
+                            .map(|(key, state)| MinecraftServerStatus::new(key, state))
+                            .collect::<Vec<_>>();
+                        let status = match networks.members(listing) {
+                            Some(_) => {
+                                // まとめた項目の名前などはpingしたかに関わらずservers.tomlの最初のエントリーに従う
+                                let primary = members.iter().find_map(|key| {
+                                    Some(MinecraftServerStatus::new(key, states.get(key)?))
+                                });
+                                primary
+                                    .and_then(|primary| networks.merge(listing, &primary, statuses))
+                            }
+                            None => statuses.pop(),
+                        };
+                        let Some(mut status) = status else {
+                            continue;
+                        };
+                        status.ranking = self.ranking.score(&status);
+                        Arc::new(status)
+                    }
+                };
+                statuses.push(status.clone());
+                published.insert(listing.to_string(), PublishedStatus { revisions, status });
+            }
+            statuses
+        };
+        drop(published);
+
+        // 人数の多い順を先に作り、他の並び順で同順位のときは人数の多い順にする
+        let by_players = self.sorter.sort(&statuses, SortKey::Players);
+        let players_order = by_players
+            .iter(Direction::Descending)
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut orders = HashMap::new();
+        for key in SortKey::ALL {
+            let sorted = match key {
+                SortKey::Players => continue,
+                SortKey::Shuffle => self.sorter.sort(&statuses, key),
+                _ => self.sorter.sort(&players_order, key),
             };
+            orders.insert(key, sorted);
+        }
+        orders.insert(SortKey::Players, by_players);
 
-            // pingを飛ばす全タスク
-            let tasks = servers_config.servers.iter().map(|server| async {
-                MinecraftServerInfo::query(server.ip.as_str(), server.port).await
-            });
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
 
-            // 一斉にpingを飛ばしてすべての結果を待つ
-            let server_status = join_all(tasks).await;
+        // 結果を反映する
+        let now = Instant::now();
+        let mut snapshots = self.snapshots.write().unwrap();
+        snapshots.push_back(Arc::new(Snapshot {
+            generation,
+            published_at: now,
+            orders,
+        }));
+        prune_snapshots(&mut snapshots, now);
+    }
 
-            let mut final_server_status = Vec::new();
+    pub async fn start(&self) {
+        tokio::join!(
+            self.registry_loop(),
+            self.probe_loop(),
+            self.publish_loop(),
+            self.compact_loop(),
+            self.stats_loop()
+        );
+    }
 
-            // (pingを飛ばすのに失敗した場合はoffline判定)
-            for (server, status) in servers_config.servers.iter().zip(server_status.into_iter()) {
-                match status {
-                    Ok(status) => {
-                        final_server_status.push(MinecraftServerStatus {
-                            ip: server.ip.clone(),
-                            port: status.port_effective as _,
-                            icon: server.icon.clone(),
-                            name: server.name.clone(),
-                            description: server.description.clone(),
-                            is_online: true,
-                            version_name: status.version_name,
-                            players_online: status.players_online,
-                            players_max: status.players_max,
-                        });
+    /// 更新があるたびに公開用の並び順を作り直す
+    /// ローテーションの区間が変わったときも作り直す
+    async fn publish_loop(&self) {
+        loop {
+            tokio::select! {
+                _ = self.dirty.notified() => {}
+                _ = sleep(self.rotation.until_next_window(unix_now())) => {}
+            }
+            self.publish();
+            sleep(PUBLISH_INTERVAL).await;
+        }
+    }
+
+    /// 履歴を定期的に集計し、保持期間を過ぎたものを削除する
+    /// 日付が変わった後はプレイヤー人数の集計も作る
+    async fn compact_loop(&self) {
+        loop {
+            let history = self.history.clone();
+            let result = spawn_blocking(move || {
+                let now = unix_now();
+                history.compact(now)?;
+                history.rollup()
+            })
+            .await;
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(error)) => warn!("Failed to compact history : {:#}", error),
+                Err(error) => warn!("Failed to compact history : {}", error),
+            }
+
+            sleep(COMPACT_INTERVAL).await;
+        }
+    }
+
+    /// 履歴から稼働状況と勢いを定期的に計算し直し、プレイヤー人数の集計を読み直す
+    async fn stats_loop(&self) {
+        loop {
+            let keys = self
+                .states
+                .read()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+            // 起動直後はservers.tomlの読み込みを待つ
+            if keys.is_empty() {
+                sleep(PUBLISH_INTERVAL).await;
+                continue;
+            }
+
+            // 大量のサーバーでも1回の問い合わせと書き込みロックが長くならないよう、まとめて計算する
+            let now = unix_now();
+            for batch in keys.chunks(STATS_BATCH) {
+                let history = self.history.clone();
+                let batch = batch.to_vec();
+                let result =
+                    spawn_blocking(move || server_stats(history.as_ref(), &batch, now)).await;
+                match result {
+                    Ok(Ok(stats)) => {
+                        let mut states = self.states.write().unwrap();
+                        for (key, stats) in stats {
+                            if let Some(state) = states.get_mut(&key) {
+                                state.revision = self.next_revision();
+                                state.uptime = stats.uptime;
+                                state.players = stats.players;
+                                state.trending_score = stats.trending_score;
+                            }
+                        }
                     }
-                    Err(_) => {
-                        final_server_status.push(MinecraftServerStatus {
-                            ip: server.ip.clone(),
-                            port: 25565,
-                            icon: server.icon.clone(),
-                            name: server.name.clone(),
-                            description: server.description.clone(),
-                            is_online: false,
-                            version_name: "".to_string(),
-                            players_online: 0,
-                            players_max: 0,
-                        });
+                    Ok(Err(error)) => warn!("Failed to compute stats : {:#}", error),
+                    Err(error) => warn!("Failed to compute stats : {}", error),
+                }
+            }
+            self.dirty.notify_one();
+
+            sleep(STATS_INTERVAL).await;
+        }
+    }
+
+    /// servers.tomlを定期的に取得し直す
+    async fn registry_loop(&self) {
+        if !self.registry.requires_signature() {
+            warn!("No public keys are configured. servers.toml is applied without verification.");
+        }
+
+        // 一度でもサーバーリストを適用できたか
+        let mut loaded = false;
+
+        loop {
+            info!("Updating servers.toml...");
+
+            match self.load_registry(false).await {
+                Ok(config) => {
+                    let added_at = self.register_servers(&config).await;
+                    self.sync_registry(&config, &added_at, !loaded);
+                    loaded = true;
+                }
+                Err(error) => {
+                    warn!("Faileed to update servers.toml : {:#}", error);
+                    if loaded {
+                        warn!("Continue with the previous servers.toml");
+                    } else {
+                        // 起動直後に取得できなければ、前回保存したものを検証し直して使う
+                        match self.load_registry(true).await {
+                            Ok(config) => {
+                                warn!("Use the cached servers.toml until the next update");
+                                let added_at = self.register_servers(&config).await;
+                                self.sync_registry(&config, &added_at, true);
+                                loaded = true;
+                            }
+                            Err(error) => {
+                                warn!("Faileed to load the cached servers.toml : {:#}", error)
+                            }
+                        }
+                        warn!("Retry in 60 seconds...");
+
+                        sleep(Duration::from_secs(60)).await;
+                        continue;
                     }
                 }
             }
 
-            // 人数の少ない順にソート
-            let mut players_reverse_order = final_server_status.clone();
-            players_reverse_order.sort_by(|a, b| a.players_online.cmp(&b.players_online));
+            sleep(self.registry.refresh_interval()).await;
+        }
+    }
+
+    /// 予定時刻になったサーバーから順にpingを飛ばす
+    /// 同時実行数は `max_concurrent_probes` までに制限する
+    async fn probe_loop(&self) {
+        let mut in_flight = FuturesUnordered::new();
+
+        loop {
+            tokio::select! {
+                Some(()) = in_flight.next(), if !in_flight.is_empty() => {}
+                (at, key) = self.scheduler.next_due(), if in_flight.len() < self.scheduler.max_concurrent() => {
+                    // サーバーリストから消えたものや、予定が入れ直されたものは捨てる
+                    let config = match self.states.read().unwrap().get(&key) {
+                        Some(state) if state.next_probe == at => state.config.clone(),
+                        _ => continue,
+                    };
+                    in_flight.push(self.probe(key, config));
+                }
+            }
+        }
+    }
 
-            // ソート結果を逆にして人数の多い順を作る
-            let mut players_order = players_reverse_order.clone();
-            players_order.reverse();
+    async fn probe(&self, key: String, config: ServerConfig) {
+        let outcome = match MinecraftServerInfo::query(&config.ip, config.port, &self.prober).await
+        {
+            Ok(info) => {
+                let transfer_check = self.check_transfer(&key, &info).await;
+                ProbeOutcome::Online(info, transfer_check)
+            }
+            // (pingを飛ばすのに失敗した場合はoffline判定)
+            Err(error) => {
+                let reason = OfflineReason::from_error(&error);
+                if reason == OfflineReason::Blocked {
+                    warn!("Blocked by address policy : {}", error);
+                }
+                ProbeOutcome::Offline(reason, format!("{:#}", error))
+            }
+        };
 
-            // 結果を反映する
-            *self.online_players_order.write().unwrap() = Arc::new(players_order);
-            *self.online_players_reverse_order.write().unwrap() = Arc::new(players_reverse_order);
+        // 履歴に残す
+        // メンテナンス中は応答があってもオンラインとしては記録しない
+        let record = match &outcome {
+            ProbeOutcome::Online(info, _) => {
+                let is_online = self.health_policy.on_success(info).is_online();
+                ProbeRecord {
+                    server: key.clone(),
+                    timestamp: unix_now(),
+                    is_online,
+                    players_online: info.players_online,
+                    players_max: info.players_max,
+                    latency_ms: Some(info.rtt_ms as i64),
+                    version_name: Some(info.version_name.clone()),
+                    offline_reason: (!is_online).then(|| "Maintenance".to_string()),
+                }
+            }
+            ProbeOutcome::Offline(reason, _) => ProbeRecord {
+                server: key.clone(),
+                timestamp: unix_now(),
+                is_online: false,
+                players_online: 0,
+                players_max: 0,
+                latency_ms: None,
+                version_name: None,
+                offline_reason: Some(reason.as_str().to_string()),
+            },
+        };
+        let history = self.history.clone();
+        spawn_blocking(move || {
+            if let Err(error) = history.record(&record) {
+                warn!("Failed to record history : {:#}", error);
+            }
+        });
 
-            info!("Getting status is completed!");
+        self.apply_probe(&key, outcome);
+    }
 
-            // 10分待って繰り返す
-            sleep(Duration::from_secs(600)).await;
+    /// 設定で有効なら、転送を受け付けるかを確かめる
+    /// 転送に対応していないバージョンや、前回から確かめ直す間隔が経っていなければ確かめない
+    async fn check_transfer(&self, key: &str, info: &MinecraftServerInfo) -> Option<TransferCheck> {
+        let interval = self.transfer_check_interval?;
+        let (_, protocol) = version::protocol_range(&info.version_name, info.version_protocol);
+        if protocol < TRANSFER_MIN_PROTOCOL {
+            return None;
+        }
+        let now = unix_now();
+        let last_checked_at = self
+            .states
+            .read()
+            .unwrap()
+            .get(key)
+            .and_then(|state| state.transfer_check)
+            .map(|check| check.checked_at);
+        if last_checked_at.is_some_and(|checked_at| now - checked_at < interval.as_secs() as i64) {
+            return None;
         }
+
+        let accepted = match info.check_transfer(protocol, &self.prober).await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                debug!("Failed to check transfer of {} : {:#}", key, error);
+                None
+            }
+        };
+        debug!("Transfer check of {} : {:?}", key, accepted);
+        Some(TransferCheck {
+            accepted,
+            checked_at: now,
+        })
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct MinecraftServerStatus {
+    /// [`ServerConfig::key`] (ネットワークをまとめた項目はネットワークのID)
+    pub key: String,
+    /// [`ServerConfig::id`]
+    pub id: String,
     pub ip: String,
     pub port: i32,
     pub icon: String,
-    pub name: String,
-    pub description: String,
+    pub name: LocalizedText,
+    pub description: LocalizedText,
     pub is_online: bool,
+    /// 再確認中かどうかを含めた稼働状態
+    pub health: Health,
     pub version_name: String,
     pub players_online: i32,
     pub players_max: i32,
+    /// オフラインと判定された理由
+    pub offline_reason: Option<OfflineReason>,
+    /// 履歴から計算した稼働状況
+    pub uptime: UptimeStats,
+    /// 集計済みのプレイヤー人数
+    pub players: PlayerStats,
+    /// 直近の人数の伸び
+    pub trending_score: Option<f64>,
+    /// 名前の読み
+    pub reading: Option<String>,
+    pub version_protocol: i32,
+    /// pingの往復時間 (ミリ秒, オフラインならNone)
+    pub latency_ms: Option<i64>,
+    /// servers.tomlに初めて載った時刻 (UNIX時間, 秒)
+    pub added_at: Option<i64>,
+    /// servers.tomlで指定された補正値
+    pub boost: f64,
+    /// Rankedの順序のスコア (公開時に計算する)
+    pub ranking: RankingScore,
+    pub tags: Vec<String>,
+    pub editions: Vec<Edition>,
+    pub categories: Vec<Category>,
+    pub languages: Vec<String>,
+    pub audience: Option<Audience>,
+    pub links: Links,
+    /// バージョン名から推定したサーバーソフトウェア
+    pub software: Option<&'static str>,
+    /// ネットワークの入口ごとの状態 (ネットワークに属さなければ空)
+    pub endpoints: Vec<EndpointStatus>,
+    /// Transferパケットで転送できるか
+    /// 確かめた結果があればそれに、なければservers.tomlの宣言に従う
+    /// 1.20.5より前のバージョンやオンラインでないものはSome(false)、どちらも分からなければNone
+    pub transferable: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineReason {
+    /// 名前解決・接続・応答のいずれかに失敗した
+    Unreachable,
+    /// 解決されたアドレスがアドレスポリシーで拒否された
+    Blocked,
+}
+
+impl MinecraftServerStatus {
+    fn new(key: &str, state: &ServerState) -> Self {
+        let config = &state.config;
+        let health = state.health.unwrap_or(Health::Offline);
+        match &state.last_info {
+            // 再確認中は最後に成功した結果を使う
+            // メンテナンス中も応答の内容は見せるが、オンラインとしては扱わない
+            Some(info) if health.is_online() || health == Health::Maintenance => Self {
+                key: key.to_string(),
+                id: config.id(),
+                ip: config.ip.clone(),
+                port: info.port_effective as _,
+                icon: config.icon.clone(),
+                name: config.name.clone(),
+                description: config.description.clone(),
+                is_online: health.is_online(),
+                health,
+                version_name: info.version_name.clone(),
+                players_online: info.players_online,
+                players_max: info.players_max,
+                offline_reason: None,
+                uptime: state.uptime.clone(),
+                players: state.players.clone(),
+                trending_score: state.trending_score,
+                reading: config.reading.clone(),
+                version_protocol: info.version_protocol,
+                software: detect_software(&info.version_name),
+                latency_ms: Some(info.rtt_ms as _),
+                added_at: state.added_at,
+                tags: config.tags.clone(),
+                editions: config.editions.clone(),
+                categories: config.categories.clone(),
+                languages: config.languages.clone(),
+                audience: config.audience,
+                links: config.links.clone(),
+                boost: config.boost,
+                ranking: RankingScore::default(),
+                endpoints: Vec::new(),
+                transferable: transferable(state, info, health),
+            },
+            _ => Self {
+                key: key.to_string(),
+                id: config.id(),
+                ip: config.ip.clone(),
+                port: 25565,
+                icon: config.icon.clone(),
+                name: config.name.clone(),
+                description: config.description.clone(),
+                is_online: false,
+                health: Health::Offline,
+                version_name: "".to_string(),
+                players_online: 0,
+                players_max: 0,
+                offline_reason: state.offline_reason,
+                uptime: state.uptime.clone(),
+                players: state.players.clone(),
+                trending_score: state.trending_score,
+                reading: config.reading.clone(),
+                version_protocol: 0,
+                software: None,
+                latency_ms: None,
+                added_at: state.added_at,
+                tags: config.tags.clone(),
+                editions: config.editions.clone(),
+                categories: config.categories.clone(),
+                languages: config.languages.clone(),
+                audience: config.audience,
+                links: config.links.clone(),
+                boost: config.boost,
+                ranking: RankingScore::default(),
+                endpoints: Vec::new(),
+                transferable: Some(false),
+            },
+        }
+    }
+}
+
+/// Transferパケットで転送できるか ([`MinecraftServerStatus::transferable`])
+fn transferable(state: &ServerState, info: &MinecraftServerInfo, health: Health) -> Option<bool> {
+    let (_, protocol) = version::protocol_range(&info.version_name, info.version_protocol);
+    if !health.is_online() || protocol < TRANSFER_MIN_PROTOCOL {
+        return Some(false);
+    }
+    state
+        .transfer_check
+        .and_then(|check| check.accepted)
+        .or(state.config.accepts_transfers)
+}
+
+impl OfflineReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unreachable => "Unreachable",
+            Self::Blocked => "Blocked",
+        }
+    }
+
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if error.downcast_ref::<BlockedAddressError>().is_some() {
+            Self::Blocked
+        } else {
+            Self::Unreachable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{server_config, server_info};
+
+    fn service() -> Service {
+        let mut config = BackendConfig::default();
+        config.history.path = ":memory:".to_string();
+        Service::new(&config).unwrap()
+    }
+
+    fn online(players_online: i32) -> ProbeOutcome {
+        let info = MinecraftServerInfo {
+            players_online,
+            ..server_info("Paper 1.21.4", 769, "")
+        };
+        ProbeOutcome::Online(info, None)
+    }
+
+    /// `snapshot` に人数の多い順で載っているサーバー
+    fn listed(snapshot: &Snapshot) -> Vec<Arc<MinecraftServerStatus>> {
+        snapshot
+            .servers(SortKey::Players)
+            .iter(Direction::Descending)
+            .cloned()
+            .collect()
+    }
+
+    fn snapshot(generation: u64, published_at: Instant) -> Arc<Snapshot> {
+        Arc::new(Snapshot {
+            generation,
+            published_at,
+            orders: HashMap::new(),
+        })
+    }
+
+    fn generations(snapshots: &VecDeque<Arc<Snapshot>>) -> Vec<u64> {
+        snapshots
+            .iter()
+            .map(|snapshot| snapshot.generation)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_snapshots_within_retention() {
+        let now = Instant::now();
+        let mut snapshots = VecDeque::new();
+        for generation in 1..=3 {
+            snapshots.push_back(snapshot(generation, now));
+        }
+        prune_snapshots(&mut snapshots, now + SNAPSHOT_RETENTION);
+        assert_eq!(generations(&snapshots), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn caps_the_number_of_snapshots() {
+        let now = Instant::now();
+        let mut snapshots = VecDeque::new();
+        for generation in 1..=30 {
+            snapshots.push_back(snapshot(generation, now));
+        }
+        prune_snapshots(&mut snapshots, now);
+        assert_eq!(snapshots.len(), RETAINED_SNAPSHOTS);
+        assert_eq!(snapshots.back().unwrap().generation, 30);
+    }
+
+    #[test]
+    fn drops_expired_snapshots() {
+        let now = Instant::now();
+        let mut snapshots = VecDeque::from([
+            snapshot(1, now),
+            snapshot(2, now + Duration::from_secs(60)),
+            snapshot(3, now + Duration::from_secs(120)),
+        ]);
+        prune_snapshots(
+            &mut snapshots,
+            now + SNAPSHOT_RETENTION + Duration::from_secs(61),
+        );
+        assert_eq!(generations(&snapshots), vec![3]);
+    }
+
+    #[test]
+    fn keeps_the_latest_snapshot() {
+        let now = Instant::now();
+        let mut snapshots = VecDeque::from([snapshot(1, now), snapshot(2, now)]);
+        prune_snapshots(&mut snapshots, now + 2 * SNAPSHOT_RETENTION);
+        assert_eq!(generations(&snapshots), vec![2]);
+    }
+
+    #[test]
+    fn publishes_probe_results_as_they_arrive() {
+        let service = service();
+        let servers = ServersConfig {
+            servers: vec![
+                server_config("ip = \"a.example.com\""),
+                server_config("ip = \"b.example.com\""),
+            ],
+        };
+        service.sync_registry(&servers, &HashMap::new(), true);
+        let initial = service.snapshot();
+
+        // まだpingしていないサーバーを待たずに、結果が届いたものから載せる
+        service.apply_probe("a.example.com", online(10));
+        service.publish();
+        let first = service.snapshot();
+        assert!(first.generation > initial.generation);
+        let statuses = listed(&first);
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].key, "a.example.com");
+        assert_eq!(statuses[0].players_online, 10);
+
+        service.apply_probe(
+            "b.example.com",
+            ProbeOutcome::Offline(OfflineReason::Unreachable, "timed out".to_string()),
+        );
+        service.publish();
+        let second = service.snapshot();
+        assert!(second.generation > first.generation);
+        let statuses = listed(&second);
+        assert_eq!(
+            statuses
+                .iter()
+                .map(|status| status.key.as_str())
+                .collect::<Vec<_>>(),
+            ["a.example.com", "b.example.com"]
+        );
+        // 変わっていないサーバーは前のスナップショットと同じステータスを使う
+        assert!(Arc::ptr_eq(&statuses[0], &listed(&first)[0]));
+
+        // ページングの途中のために古いスナップショットも残る
+        assert!(service.snapshot_at(first.generation).is_some());
+    }
+
+    #[test]
+    fn drops_results_of_removed_servers() {
+        let service = service();
+        let servers = ServersConfig {
+            servers: vec![server_config("ip = \"a.example.com\"")],
+        };
+        service.sync_registry(&servers, &HashMap::new(), true);
+        service.apply_probe("a.example.com", online(10));
+        service.sync_registry(&ServersConfig { servers: vec![] }, &HashMap::new(), false);
+
+        service.apply_probe("a.example.com", online(20));
+        service.publish();
+        assert!(listed(&service.snapshot()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn finds_server_details_by_id() {
+        let service = service();
+        let servers = ServersConfig {
+            servers: vec![
+                server_config("ip = \"a.example.com\"\nid = \"a\""),
+                server_config("ip = \"b.example.com\"\nport = 25566"),
+            ],
+        };
+        service.sync_registry(&servers, &HashMap::new(), true);
+        let info = MinecraftServerInfo {
+            motd_legacy: "§aHello".to_string(),
+            ..server_info("Paper 1.21.4", 769, "§aHello")
+        };
+        service.apply_probe("a.example.com", ProbeOutcome::Online(info, None));
+        service.apply_probe(
+            "b.example.com:25566",
+            ProbeOutcome::Offline(OfflineReason::Unreachable, "timed out".to_string()),
+        );
+        service.publish();
+
+        let detail = service.server_detail("a").await.unwrap().unwrap();
+        assert_eq!(detail.status.key, "a.example.com");
+        let motd = detail.motd.unwrap();
+        assert_eq!(motd.plain, "Hello");
+        assert_eq!(motd.legacy, "§aHello");
+        assert_eq!(
+            detail.probe.resolved_address.as_deref(),
+            Some("203.0.113.1:25565")
+        );
+        assert_eq!(detail.probe.consecutive_failures, 0);
+
+        // IDを省略したエントリーはホストとポートで指定する
+        let detail = service
+            .server_detail("b.example.com:25566")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(detail.motd.is_none());
+        assert_eq!(detail.probe.consecutive_failures, 1);
+        assert_eq!(detail.probe.last_error.as_deref(), Some("timed out"));
+
+        assert!(
+            service
+                .server_detail("a.example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            service
+                .server_detail("b.example.com")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
 }
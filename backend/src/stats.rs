@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::storage::{HistoryStore, PlayerSummary, RollupPeriod, TimelinePoint};
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+/// これ以上間が空いた点同士はデータなしとして扱う
+/// (バックエンドが止まっていた期間を稼働率に含めないため)
+const MAX_SAMPLE_GAP: i64 = 2 * HOUR;
+
+/// 稼働率などの統計を取る最長の期間
+pub const STATS_WINDOW: i64 = 30 * DAY;
+
+/// 勢いを測る直近の期間
+const TRENDING_RECENT: i64 = DAY;
+
+/// 勢いの基準にする期間 (直近の期間の前)
+const TRENDING_BASELINE: i64 = 7 * DAY;
+
+/// 勢いの計算で基準の人数に足す人数
+/// 人数の少ないサーバーが1人増えただけで上位にならないようにする
+const TRENDING_SMOOTHING: f64 = 5.0;
+
+/// 履歴から計算した稼働状況
+#[derive(Debug, Clone, Default)]
+pub struct UptimeStats {
+    /// 直近24時間の稼働率 (0.0〜1.0, データがなければNone)
+    pub uptime_24h: Option<f64>,
+    /// 直近7日間の稼働率
+    pub uptime_7d: Option<f64>,
+    /// 直近30日間の稼働率
+    pub uptime_30d: Option<f64>,
+    /// 最後にオンラインだった時刻 (UNIX時間, 秒)
+    pub last_seen_online: Option<i64>,
+    /// 直近30日間で最も長い停止時間 (秒)
+    pub longest_outage_secs: i64,
+    /// 直近30日間の平均故障間隔 (秒, 停止またはオンラインの期間がなければNone)
+    pub mtbf_secs: Option<i64>,
+}
+
+/// 集計済みのプレイヤー人数
+#[derive(Debug, Clone, Default)]
+pub struct PlayerStats {
+    /// 前日 (UTC) の集計
+    pub daily: Option<PlayerSummary>,
+    /// 前日までの7日間の集計
+    pub weekly: Option<PlayerSummary>,
+}
+
+/// 時系列の各点が表す区間
+struct Span {
+    start: i64,
+    end: i64,
+    online_ratio: f64,
+    players_avg: f64,
+}
+
+/// 時系列を「次の点まで同じ状態が続いた」とみなして区間に分ける
+fn spans(timeline: &[TimelinePoint], now: i64) -> Vec<Span> {
+    timeline
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let next = timeline
+                .get(i + 1)
+                .map(|next| next.timestamp)
+                .unwrap_or(now);
+            Span {
+                start: point.timestamp,
+                end: next.min(point.timestamp + MAX_SAMPLE_GAP),
+                online_ratio: point.online_ratio,
+                players_avg: point.players_avg,
+            }
+        })
+        .filter(|span| span.end > span.start)
+        .collect()
+}
+
+/// `from` 以上 `to` 未満の時間で重み付けした平均
+fn weighted_average(
+    spans: &[Span],
+    from: i64,
+    to: i64,
+    value: impl Fn(&Span) -> f64,
+) -> Option<f64> {
+    let mut total = 0;
+    let mut sum = 0.0;
+    for span in spans.iter() {
+        let duration = span.end.min(to) - span.start.max(from);
+        if duration > 0 {
+            total += duration;
+            sum += value(span) * duration as f64;
+        }
+    }
+    (total > 0).then(|| sum / total as f64)
+}
+
+/// `since` 以降の時間で重み付けした稼働率
+fn uptime_since(spans: &[Span], since: i64) -> Option<f64> {
+    weighted_average(spans, since, i64::MAX, |span| span.online_ratio)
+}
+
+/// 直近30日間の時系列から稼働状況を計算する
+pub fn uptime_stats(
+    timeline: &[TimelinePoint],
+    last_seen_online: Option<i64>,
+    now: i64,
+) -> UptimeStats {
+    let spans = spans(timeline, now);
+
+    // 停止区間 (完全にオフラインだった区間の連続) を数える
+    let mut outages = Vec::new();
+    let mut outage_start: Option<i64> = None;
+    let mut previous_end: Option<i64> = None;
+    let mut online_secs = 0;
+    for span in spans.iter() {
+        // データの欠けている期間をまたいだら区切る
+        if let (Some(start), Some(end)) = (outage_start, previous_end)
+            && span.start > end
+        {
+            outages.push(end - start);
+            outage_start = None;
+        }
+
+        if span.online_ratio == 0.0 {
+            outage_start.get_or_insert(span.start);
+        } else {
+            if let Some(start) = outage_start.take() {
+                outages.push(span.start - start);
+            }
+            online_secs += ((span.end - span.start) as f64 * span.online_ratio) as i64;
+        }
+        previous_end = Some(span.end);
+    }
+    if let (Some(start), Some(end)) = (outage_start, previous_end) {
+        outages.push(end - start);
+    }
+
+    UptimeStats {
+        uptime_24h: uptime_since(&spans, now - DAY),
+        uptime_7d: uptime_since(&spans, now - 7 * DAY),
+        uptime_30d: uptime_since(&spans, now - STATS_WINDOW),
+        last_seen_online,
+        longest_outage_secs: outages.iter().copied().max().unwrap_or(0),
+        mtbf_secs: (!outages.is_empty() && online_secs > 0)
+            .then(|| online_secs / outages.len() as i64),
+    }
+}
+
+/// 履歴から計算したサーバーの統計
+#[derive(Debug, Clone, Default)]
+pub struct ServerStats {
+    pub uptime: UptimeStats,
+    pub players: PlayerStats,
+    /// [`trending_score`]
+    pub trending_score: Option<f64>,
+}
+
+/// `servers` の統計をまとめて計算する
+/// プレイヤー人数は最新の集計を使い、集計が途絶えて古くなったものは使わない
+pub fn server_stats(
+    history: &dyn HistoryStore,
+    servers: &[String],
+    now: i64,
+) -> Result<HashMap<String, ServerStats>> {
+    let mut timelines = history.timelines(servers, now - STATS_WINDOW, now + 1)?;
+    let last_online = history.last_online(servers)?;
+    let since = now - 2 * DAY;
+    let mut daily = history.player_summaries(servers, RollupPeriod::Day, since)?;
+    let mut weekly = history.player_summaries(servers, RollupPeriod::Week, since)?;
+
+    Ok(servers
+        .iter()
+        .map(|server| {
+            let timeline = timelines.remove(server).unwrap_or_default();
+            let stats = ServerStats {
+                uptime: uptime_stats(&timeline, last_online.get(server).copied(), now),
+                players: PlayerStats {
+                    daily: daily.remove(server),
+                    weekly: weekly.remove(server),
+                },
+                trending_score: trending_score(&timeline, now),
+            };
+            (server.clone(), stats)
+        })
+        .collect())
+}
+
+/// 直近の人数がそのサーバー自身の普段の人数からどれだけ増えたか
+/// 増えた人数を普段の人数で割るので、小さいサーバーの伸びも大きいサーバーと同じように評価される
+/// どちらかの期間のデータがなければNone
+pub fn trending_score(timeline: &[TimelinePoint], now: i64) -> Option<f64> {
+    let spans = spans(timeline, now);
+    let recent_start = now - TRENDING_RECENT;
+    let recent = weighted_average(&spans, recent_start, now, |span| span.players_avg)?;
+    let baseline = weighted_average(
+        &spans,
+        recent_start - TRENDING_BASELINE,
+        recent_start,
+        |span| span.players_avg,
+    )?;
+    Some((recent - baseline) / (baseline + TRENDING_SMOOTHING))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::{RetentionPolicy, SqliteHistoryStore},
+        testing::{hourly_timeline, online_probe, timeline_point},
+    };
+
+    const NOW: i64 = 19676 * DAY;
+
+    #[test]
+    fn uptime_without_data() {
+        let stats = uptime_stats(&[], None, NOW);
+        assert_eq!(stats.uptime_24h, None);
+        assert_eq!(stats.uptime_30d, None);
+        assert_eq!(stats.longest_outage_secs, 0);
+        assert_eq!(stats.mtbf_secs, None);
+    }
+
+    #[test]
+    fn uptime_counts_outages() {
+        let timeline = [
+            timeline_point(NOW - 4 * HOUR, 1.0, 0.0),
+            timeline_point(NOW - 3 * HOUR, 0.0, 0.0),
+            timeline_point(NOW - 2 * HOUR, 0.0, 0.0),
+            timeline_point(NOW - HOUR, 1.0, 0.0),
+        ];
+        let stats = uptime_stats(&timeline, Some(NOW - HOUR), NOW);
+        assert_eq!(stats.uptime_24h, Some(0.5));
+        assert_eq!(stats.uptime_7d, Some(0.5));
+        assert_eq!(stats.last_seen_online, Some(NOW - HOUR));
+        assert_eq!(stats.longest_outage_secs, 2 * HOUR);
+        assert_eq!(stats.mtbf_secs, Some(2 * HOUR));
+    }
+
+    #[test]
+    fn uptime_uses_hourly_ratios() {
+        let timeline = [
+            timeline_point(NOW - 2 * HOUR, 0.5, 0.0),
+            timeline_point(NOW - HOUR, 1.0, 0.0),
+        ];
+        let stats = uptime_stats(&timeline, None, NOW);
+        assert_eq!(stats.uptime_24h, Some(0.75));
+        assert_eq!(stats.longest_outage_secs, 0);
+        assert_eq!(stats.mtbf_secs, None);
+    }
+
+    #[test]
+    fn uptime_ignores_gaps_in_data() {
+        // 間の空いた期間は停止にも稼働にも数えない
+        let timeline = [
+            timeline_point(NOW - 10 * HOUR, 0.0, 0.0),
+            timeline_point(NOW - HOUR, 1.0, 0.0),
+        ];
+        let stats = uptime_stats(&timeline, None, NOW);
+        assert_eq!(stats.uptime_24h, Some(1.0 / 3.0));
+        assert_eq!(stats.longest_outage_secs, MAX_SAMPLE_GAP);
+    }
+
+    #[test]
+    fn uptime_windows() {
+        let mut timeline = hourly_timeline(NOW - 3 * DAY, NOW - 2 * DAY, 0.0);
+        for point in timeline.iter_mut() {
+            point.online_ratio = 0.0;
+        }
+        timeline.extend(hourly_timeline(NOW - 2 * DAY, NOW, 0.0));
+        let stats = uptime_stats(&timeline, None, NOW);
+        assert_eq!(stats.uptime_24h, Some(1.0));
+        assert_eq!(stats.uptime_7d, Some(2.0 / 3.0));
+        assert_eq!(stats.longest_outage_secs, DAY);
+    }
+
+    #[test]
+    fn trending_needs_both_periods() {
+        assert_eq!(
+            trending_score(&hourly_timeline(NOW - DAY, NOW, 10.0), NOW),
+            None
+        );
+        assert_eq!(
+            trending_score(
+                &hourly_timeline(NOW - 8 * DAY, NOW - DAY - 2 * HOUR, 10.0),
+                NOW
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn trending_compares_with_the_baseline() {
+        let mut timeline = hourly_timeline(NOW - 8 * DAY, NOW - DAY, 10.0);
+        timeline.extend(hourly_timeline(NOW - DAY, NOW, 25.0));
+        assert_eq!(trending_score(&timeline, NOW), Some(1.0));
+
+        let flat = hourly_timeline(NOW - 8 * DAY, NOW, 10.0);
+        assert_eq!(trending_score(&flat, NOW), Some(0.0));
+
+        let mut falling = hourly_timeline(NOW - 8 * DAY, NOW - DAY, 10.0);
+        falling.extend(hourly_timeline(NOW - DAY, NOW, 0.0));
+        assert_eq!(trending_score(&falling, NOW), Some(-10.0 / 15.0));
+    }
+
+    #[test]
+    fn trending_dampens_small_servers() {
+        // 普段0人のサーバーに1人来ただけでは大きくならない
+        let mut small = hourly_timeline(NOW - 8 * DAY, NOW - DAY, 0.0);
+        small.extend(hourly_timeline(NOW - DAY, NOW, 1.0));
+        assert_eq!(trending_score(&small, NOW), Some(0.2));
+
+        // 同じ割合で増えたなら規模によらず同じくらいになる
+        let mut large = hourly_timeline(NOW - 8 * DAY, NOW - DAY, 100.0);
+        large.extend(hourly_timeline(NOW - DAY, NOW, 200.0));
+        let mut medium = hourly_timeline(NOW - 8 * DAY, NOW - DAY, 50.0);
+        medium.extend(hourly_timeline(NOW - DAY, NOW, 100.0));
+        let large = trending_score(&large, NOW).unwrap();
+        let medium = trending_score(&medium, NOW).unwrap();
+        assert!(large > medium && large - medium < 0.1);
+    }
+
+    #[test]
+    fn server_stats_use_recent_rollups() {
+        let store = SqliteHistoryStore::open(
+            ":memory:",
+            RetentionPolicy {
+                raw: DAY,
+                hourly: STATS_WINDOW,
+            },
+        )
+        .unwrap();
+        let start = NOW - 2 * DAY;
+        for hour in 0..24 {
+            store
+                .record(&online_probe("a", start + hour * HOUR, hour as i32, 10))
+                .unwrap();
+        }
+        store.compact(NOW).unwrap();
+        store.rollup().unwrap();
+
+        let servers = ["a".to_string(), "b".to_string()];
+        let stats = server_stats(&store, &servers, NOW).unwrap();
+        let daily = stats["a"].players.daily.unwrap();
+        assert_eq!(daily.average, 11.5);
+        assert_eq!(daily.peak, 23);
+        assert_eq!(stats["a"].players.weekly.unwrap().peak, 23);
+        assert!(stats["b"].players.daily.is_none());
+
+        // 集計が途絶えて古くなったものは使わない
+        let stats = server_stats(&store, &servers, NOW + 3 * DAY).unwrap();
+        assert!(stats["a"].players.daily.is_none());
+        assert!(stats["a"].players.weekly.is_none());
+    }
+}
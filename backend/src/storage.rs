@@ -0,0 +1,1003 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, ToSql, params, params_from_iter};
+
+use crate::config::HistoryConfig;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+/// 現在のUNIX時間 (秒)
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// 1回のpingの記録
+#[derive(Debug, Clone)]
+pub struct ProbeRecord {
+    /// [`crate::config::ServerConfig::key`]
+    pub server: String,
+    /// UNIX時間 (秒)
+    pub timestamp: i64,
+    pub is_online: bool,
+    pub players_online: i32,
+    pub players_max: i32,
+    /// pingの往復時間 (ミリ秒)
+    pub latency_ms: Option<i64>,
+    pub version_name: Option<String>,
+    pub offline_reason: Option<String>,
+}
+
+/// 読み取り専用の接続の数
+/// WALモードなので、書き込み中もAPIや統計の計算からの読み取りは並行して進む
+const READ_CONNECTIONS: usize = 4;
+
+/// 1回の取得で返す最大の点数 (超える場合は新しい方を返す)
+const MAX_HISTORY_POINTS: i64 = 5000;
+
+/// 履歴を返すときの区間の長さ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// pingごと (生データの保持期間内のみ)
+    Raw,
+    /// 1時間ごと
+    Hour,
+    /// 1日ごと (UTC)
+    Day,
+}
+
+impl Resolution {
+    /// 区間の長さ (秒)
+    fn bucket(self) -> i64 {
+        match self {
+            Self::Raw => 1,
+            Self::Hour => HOUR,
+            Self::Day => DAY,
+        }
+    }
+}
+
+/// 履歴の1区間
+#[derive(Debug, Clone)]
+pub struct HistoryPoint {
+    /// 区間の開始時刻 (UNIX時間, 秒)
+    pub timestamp: i64,
+    /// 区間内の平均プレイヤー人数
+    pub players_avg: f64,
+    /// 区間内の最大プレイヤー人数
+    pub players_peak: i32,
+    /// 区間内でオンラインだった割合 (0.0〜1.0)
+    pub online_ratio: f64,
+}
+
+/// 統計用の時系列の1点 (1時間ごと)
+#[derive(Debug, Clone)]
+pub struct TimelinePoint {
+    /// 区間の開始時刻 (UNIX時間, 秒)
+    pub timestamp: i64,
+    /// オンラインだった割合
+    pub online_ratio: f64,
+    /// 平均プレイヤー人数
+    pub players_avg: f64,
+}
+
+/// プレイヤー人数の集計期間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollupPeriod {
+    /// 1日 (UTC)
+    Day,
+    /// 7日間
+    Week,
+}
+
+impl RollupPeriod {
+    /// 期間の長さ (秒)
+    pub fn span(self) -> i64 {
+        match self {
+            Self::Day => DAY,
+            Self::Week => 7 * DAY,
+        }
+    }
+}
+
+/// ある期間のプレイヤー人数の集計
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerSummary {
+    /// 平均プレイヤー人数
+    pub average: f64,
+    /// 最大プレイヤー人数
+    pub peak: i32,
+    /// 1時間ごとの平均人数の中央値
+    /// 一瞬だけ人数を増やしても動きにくい
+    pub median: f64,
+}
+
+/// ある期間のpingの往復時間の集計 (ミリ秒)
+#[derive(Debug, Clone, Copy)]
+pub struct LatencySummary {
+    pub min: i64,
+    pub average: f64,
+    pub max: i64,
+    /// 集計したpingの回数
+    pub samples: i64,
+}
+
+/// pingの履歴の保存先
+/// テストではSQLiteのインメモリDB (`:memory:`) を使える
+pub trait HistoryStore: Send + Sync {
+    /// pingの結果を1件追加する
+    fn record(&self, record: &ProbeRecord) -> Result<()>;
+
+    /// 古い生データを1時間ごとの集計にまとめ、保持期間を過ぎたものを削除する
+    fn compact(&self, now: i64) -> Result<()>;
+
+    /// `from` 以上 `to` 未満の履歴を `resolution` ごとにまとめて古い順に返す
+    /// 点が [`MAX_HISTORY_POINTS`] より多ければ新しい方から返す
+    fn history(
+        &self,
+        server: &str,
+        from: i64,
+        to: i64,
+        resolution: Resolution,
+    ) -> Result<Vec<HistoryPoint>>;
+
+    /// `servers` の `from` 以上 `to` 未満の時系列を、サーバーごとに古い順で返す
+    /// 集計済みの時間帯は1時間ごとの集計を使い、それ以降は生データを1時間ごとにまとめる
+    fn timelines(
+        &self,
+        servers: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<HashMap<String, Vec<TimelinePoint>>>;
+
+    /// `servers` が最後にオンラインだった時刻 (一度もなければ含まない)
+    fn last_online(&self, servers: &[String]) -> Result<HashMap<String, i64>>;
+
+    /// servers.tomlに初めて載った時刻を返す
+    /// まだ記録のないサーバーは `now` で記録する
+    fn register_servers(&self, servers: &[String], now: i64) -> Result<HashMap<String, i64>>;
+
+    /// 1時間ごとの集計が終わった日について、その日までの各期間のプレイヤー人数を集計する
+    /// [`HistoryStore::compact`] の後に呼ぶ
+    fn rollup(&self) -> Result<()>;
+
+    /// `servers` の `since` 以降に終わった期間のうち、最新のプレイヤー人数の集計
+    /// 集計がなければ含まない
+    fn player_summaries(
+        &self,
+        servers: &[String],
+        period: RollupPeriod,
+        since: i64,
+    ) -> Result<HashMap<String, PlayerSummary>>;
+
+    /// `since` 以降に成功したpingの往復時間の集計
+    /// 生データの保持期間より前は含まない
+    fn latency_summary(&self, server: &str, since: i64) -> Result<Option<LatencySummary>>;
+}
+
+/// 保持期間
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// 生データを残す期間 (秒)
+    pub raw: i64,
+    /// 1時間ごとの集計を残す期間 (秒)
+    pub hourly: i64,
+}
+
+impl RetentionPolicy {
+    pub fn new(config: &HistoryConfig) -> Self {
+        Self {
+            raw: config.raw_retention_days as i64 * DAY,
+            hourly: config.hourly_retention_days as i64 * DAY,
+        }
+    }
+}
+
+/// ## SQLiteによる履歴の保存
+///
+/// * `probes`: pingごとの生データ
+/// * `probes_hourly`: 1時間ごとの集計 (生データの保持期間を過ぎても残る)
+/// * `player_rollups`: 1日ごとに作るプレイヤー人数の集計
+/// * `servers`: サーバーがservers.tomlに初めて載った時刻
+/// * `meta`: 集計済みの時刻などの管理情報
+///
+/// 書き込みは1つの接続で行い、読み取りは別に開いた読み取り専用の接続で行う
+pub struct SqliteHistoryStore {
+    connection: Mutex<Connection>,
+    /// 読み取り専用の接続 (インメモリDBでは共有できないので空)
+    readers: Vec<Mutex<Connection>>,
+    /// 読み取り専用の接続がすべて使用中のときに待つ接続の番号
+    next_reader: AtomicUsize,
+    retention: RetentionPolicy,
+}
+
+impl SqliteHistoryStore {
+    /// `path` に `:memory:` を指定するとインメモリDBになる
+    pub fn open(path: impl AsRef<Path>, retention: RetentionPolicy) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "
+            PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+
+            CREATE TABLE IF NOT EXISTS probes (
+                server          TEXT    NOT NULL,
+                timestamp       INTEGER NOT NULL,
+                is_online       INTEGER NOT NULL,
+                players_online  INTEGER NOT NULL,
+                players_max     INTEGER NOT NULL,
+                latency_ms      INTEGER,
+                version_name    TEXT,
+                offline_reason  TEXT
+            );
+            CREATE INDEX IF NOT EXISTS probes_server_timestamp ON probes (server, timestamp);
+            CREATE INDEX IF NOT EXISTS probes_timestamp ON probes (timestamp);
+
+            CREATE TABLE IF NOT EXISTS probes_hourly (
+                server          TEXT    NOT NULL,
+                hour            INTEGER NOT NULL,
+                samples         INTEGER NOT NULL,
+                online_samples  INTEGER NOT NULL,
+                players_avg     REAL    NOT NULL,
+                players_peak    INTEGER NOT NULL,
+                latency_avg     REAL,
+                PRIMARY KEY (server, hour)
+            );
+            CREATE INDEX IF NOT EXISTS probes_hourly_hour ON probes_hourly (hour);
+
+            CREATE TABLE IF NOT EXISTS player_rollups (
+                server          TEXT    NOT NULL,
+                span            INTEGER NOT NULL,
+                period_end      INTEGER NOT NULL,
+                samples         INTEGER NOT NULL,
+                players_avg     REAL    NOT NULL,
+                players_peak    INTEGER NOT NULL,
+                players_median  REAL    NOT NULL,
+                PRIMARY KEY (server, span, period_end)
+            );
+            CREATE INDEX IF NOT EXISTS player_rollups_period_end ON player_rollups (period_end);
+
+            CREATE TABLE IF NOT EXISTS servers (
+                server          TEXT    PRIMARY KEY,
+                added_at        INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS meta (
+                key     TEXT PRIMARY KEY,
+                value   INTEGER NOT NULL
+            );
+            ",
+        )?;
+
+        // テーブルを作った後に開く
+        let readers = if path == Path::new(":memory:") {
+            Vec::new()
+        } else {
+            (0..READ_CONNECTIONS)
+                .map(|_| {
+                    let reader = Connection::open(path)?;
+                    reader.execute_batch("PRAGMA query_only = ON;")?;
+                    Ok(Mutex::new(reader))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            readers,
+            next_reader: AtomicUsize::new(0),
+            retention,
+        })
+    }
+
+    /// 読み取りに使う接続
+    /// 空いているものがなければ順番に割り当てて待つ
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        if self.readers.is_empty() {
+            return self.connection.lock().unwrap();
+        }
+        for reader in self.readers.iter() {
+            if let Ok(reader) = reader.try_lock() {
+                return reader;
+            }
+        }
+        let index = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[index].lock().unwrap()
+    }
+}
+
+impl HistoryStore for SqliteHistoryStore {
+    fn record(&self, record: &ProbeRecord) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO probes (
+                server, timestamp, is_online, players_online, players_max,
+                latency_ms, version_name, offline_reason
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record.server,
+                record.timestamp,
+                record.is_online,
+                record.players_online,
+                record.players_max,
+                record.latency_ms,
+                record.version_name,
+                record.offline_reason,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn compact(&self, now: i64) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        // 集計は終わった時間帯のみを対象にする
+        let current_hour = now - now.rem_euclid(HOUR);
+        let aggregated_until = meta(&transaction, "aggregated_until")?;
+
+        if aggregated_until < current_hour {
+            transaction.execute(
+                "INSERT OR REPLACE INTO probes_hourly (
+                    server, hour, samples, online_samples, players_avg, players_peak, latency_avg
+                )
+                SELECT
+                    server,
+                    timestamp - timestamp % 3600 AS hour,
+                    COUNT(*),
+                    SUM(is_online),
+                    AVG(players_online),
+                    MAX(players_online),
+                    AVG(latency_ms)
+                FROM probes
+                WHERE timestamp >= ?1 AND timestamp < ?2
+                GROUP BY server, hour",
+                params![aggregated_until, current_hour],
+            )?;
+            set_meta(&transaction, "aggregated_until", current_hour)?;
+        }
+
+        transaction.execute(
+            "DELETE FROM probes WHERE timestamp < ?1",
+            params![now - self.retention.raw],
+        )?;
+        transaction.execute(
+            "DELETE FROM probes_hourly WHERE hour < ?1",
+            params![now - self.retention.hourly],
+        )?;
+        transaction.execute(
+            "DELETE FROM player_rollups WHERE period_end < ?1",
+            params![now - self.retention.hourly],
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn history(
+        &self,
+        server: &str,
+        from: i64,
+        to: i64,
+        resolution: Resolution,
+    ) -> Result<Vec<HistoryPoint>> {
+        let connection = self.reader();
+        let aggregated_until = meta(&connection, "aggregated_until")?;
+
+        let row_to_point = |row: &rusqlite::Row| {
+            Ok(HistoryPoint {
+                timestamp: row.get(0)?,
+                players_avg: row.get(1)?,
+                players_peak: row.get(2)?,
+                online_ratio: row.get(3)?,
+            })
+        };
+
+        let mut points = if resolution == Resolution::Raw {
+            let mut statement = connection.prepare_cached(
+                "SELECT timestamp, CAST(players_online AS REAL), players_online, CAST(is_online AS REAL)
+                FROM probes
+                WHERE server = ?1 AND timestamp >= ?2 AND timestamp < ?3
+                ORDER BY timestamp DESC
+                LIMIT ?4",
+            )?;
+            statement
+                .query_map(params![server, from, to, MAX_HISTORY_POINTS], row_to_point)?
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            // 集計済みの時間帯は1時間ごとの集計から、それ以降は生データから作る
+            let mut statement = connection.prepare_cached(
+                "SELECT
+                    hour - hour % ?5 AS bucket,
+                    SUM(players_avg * samples) / SUM(samples),
+                    MAX(players_peak),
+                    CAST(SUM(online_samples) AS REAL) / SUM(samples)
+                FROM (
+                    SELECT hour, samples, online_samples, players_avg, players_peak
+                    FROM probes_hourly
+                    WHERE server = ?1 AND hour >= ?2 AND hour < MIN(?3, ?4)
+                    UNION ALL
+                    SELECT timestamp, 1, is_online, players_online, players_online
+                    FROM probes
+                    WHERE server = ?1 AND timestamp >= MAX(?2, ?4) AND timestamp < ?3
+                )
+                GROUP BY bucket
+                ORDER BY bucket DESC
+                LIMIT ?6",
+            )?;
+            statement
+                .query_map(
+                    params![
+                        server,
+                        from,
+                        to,
+                        aggregated_until,
+                        resolution.bucket(),
+                        MAX_HISTORY_POINTS
+                    ],
+                    row_to_point,
+                )?
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        // 上限を超えたときに新しい方を残すため、新しい順に取り出してから並べ直す
+        points.reverse();
+        Ok(points)
+    }
+
+    fn timelines(
+        &self,
+        servers: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<HashMap<String, Vec<TimelinePoint>>> {
+        let connection = self.reader();
+        let aggregated_until = meta(&connection, "aggregated_until")?;
+
+        let servers_in = placeholders(4, servers.len());
+        let mut statement = connection.prepare(&format!(
+            "SELECT server, hour, CAST(online_samples AS REAL) / samples, players_avg
+            FROM probes_hourly
+            WHERE server IN ({servers_in}) AND hour >= ?1 AND hour < MIN(?2, ?3)
+            UNION ALL
+            SELECT server, timestamp - timestamp % 3600 AS hour, AVG(is_online), AVG(players_online)
+            FROM probes
+            WHERE server IN ({servers_in}) AND timestamp >= MAX(?1, ?3) AND timestamp < ?2
+            GROUP BY server, hour
+            ORDER BY 1, 2"
+        ))?;
+        let mut params: Vec<&dyn ToSql> = vec![&from, &to, &aggregated_until];
+        params.extend(servers.iter().map(|server| server as &dyn ToSql));
+        let mut rows = statement.query(params_from_iter(params))?;
+
+        let mut timelines = HashMap::<String, Vec<TimelinePoint>>::new();
+        while let Some(row) = rows.next()? {
+            timelines
+                .entry(row.get(0)?)
+                .or_default()
+                .push(TimelinePoint {
+                    timestamp: row.get(1)?,
+                    online_ratio: row.get(2)?,
+                    players_avg: row.get(3)?,
+                });
+        }
+        Ok(timelines)
+    }
+
+    fn last_online(&self, servers: &[String]) -> Result<HashMap<String, i64>> {
+        let connection = self.reader();
+        let servers_in = placeholders(1, servers.len());
+        let mut statement = connection.prepare(&format!(
+            "SELECT server, MAX(last_online) FROM (
+                SELECT server, MAX(timestamp) AS last_online
+                FROM probes
+                WHERE server IN ({servers_in}) AND is_online = 1
+                GROUP BY server
+                UNION ALL
+                SELECT server, MAX(hour) + 3599
+                FROM probes_hourly
+                WHERE server IN ({servers_in}) AND online_samples > 0
+                GROUP BY server
+            )
+            GROUP BY server"
+        ))?;
+        let last_online = statement
+            .query_map(params_from_iter(servers), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(last_online)
+    }
+
+    fn register_servers(&self, servers: &[String], now: i64) -> Result<HashMap<String, i64>> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let mut added_at = HashMap::new();
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT OR IGNORE INTO servers (server, added_at) VALUES (?1, ?2)",
+            )?;
+            let mut select =
+                transaction.prepare_cached("SELECT added_at FROM servers WHERE server = ?1")?;
+            for server in servers {
+                insert.execute(params![server, now])?;
+                added_at.insert(
+                    server.clone(),
+                    select.query_row(params![server], |row| row.get(0))?,
+                );
+            }
+        }
+        transaction.commit()?;
+        Ok(added_at)
+    }
+
+    fn rollup(&self) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        // 1時間ごとの集計が揃っている日までを対象にする
+        let aggregated_until = meta(&transaction, "aggregated_until")?;
+        let target = aggregated_until - aggregated_until.rem_euclid(DAY);
+        let rolled_up_until = meta(&transaction, "rolled_up_until")?;
+
+        // 初回や長く止まっていた後は、最も長い期間の分だけさかのぼる
+        let mut period_end = (rolled_up_until + DAY).max(target - RollupPeriod::Week.span() + DAY);
+        while period_end <= target {
+            let mut hours = HashMap::<String, Vec<HourlyPlayers>>::new();
+            {
+                let mut statement = transaction.prepare_cached(
+                    "SELECT server, hour, samples, players_avg, players_peak
+                    FROM probes_hourly
+                    WHERE hour >= ?1 AND hour < ?2",
+                )?;
+                let mut rows =
+                    statement.query(params![period_end - RollupPeriod::Week.span(), period_end])?;
+                while let Some(row) = rows.next()? {
+                    hours.entry(row.get(0)?).or_default().push(HourlyPlayers {
+                        hour: row.get(1)?,
+                        samples: row.get(2)?,
+                        players_avg: row.get(3)?,
+                        players_peak: row.get(4)?,
+                    });
+                }
+            }
+
+            for (server, hours) in hours.iter() {
+                for period in [RollupPeriod::Day, RollupPeriod::Week] {
+                    let since = period_end - period.span();
+                    let hours = hours
+                        .iter()
+                        .filter(|hour| hour.hour >= since)
+                        .collect::<Vec<_>>();
+                    let Some((samples, summary)) = summarize(&hours) else {
+                        continue;
+                    };
+                    transaction.execute(
+                        "INSERT OR REPLACE INTO player_rollups (
+                            server, span, period_end, samples, players_avg, players_peak, players_median
+                        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            server,
+                            period.span(),
+                            period_end,
+                            samples,
+                            summary.average,
+                            summary.peak,
+                            summary.median,
+                        ],
+                    )?;
+                }
+            }
+
+            set_meta(&transaction, "rolled_up_until", period_end)?;
+            period_end += DAY;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn player_summaries(
+        &self,
+        servers: &[String],
+        period: RollupPeriod,
+        since: i64,
+    ) -> Result<HashMap<String, PlayerSummary>> {
+        let connection = self.reader();
+        let servers_in = placeholders(3, servers.len());
+        // サーバーごとに最新の期間を選ぶ (SQLiteではMAXと同じ行の他の列が取れる)
+        let mut statement = connection.prepare(&format!(
+            "SELECT server, players_avg, players_peak, players_median, MAX(period_end)
+            FROM player_rollups
+            WHERE server IN ({servers_in}) AND span = ?1 AND period_end >= ?2
+            GROUP BY server"
+        ))?;
+        let span = period.span();
+        let mut params: Vec<&dyn ToSql> = vec![&span, &since];
+        params.extend(servers.iter().map(|server| server as &dyn ToSql));
+        let summaries = statement
+            .query_map(params_from_iter(params), |row| {
+                Ok((
+                    row.get(0)?,
+                    PlayerSummary {
+                        average: row.get(1)?,
+                        peak: row.get(2)?,
+                        median: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(summaries)
+    }
+
+    fn latency_summary(&self, server: &str, since: i64) -> Result<Option<LatencySummary>> {
+        let connection = self.reader();
+        let summary = connection.query_row(
+            "SELECT MIN(latency_ms), AVG(latency_ms), MAX(latency_ms), COUNT(latency_ms)
+            FROM probes
+            WHERE server = ?1 AND timestamp >= ?2 AND latency_ms IS NOT NULL",
+            params![server, since],
+            |row| {
+                let samples: i64 = row.get(3)?;
+                if samples == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(LatencySummary {
+                    min: row.get(0)?,
+                    average: row.get(1)?,
+                    max: row.get(2)?,
+                    samples,
+                }))
+            },
+        )?;
+        Ok(summary)
+    }
+}
+
+/// 集計用の1時間ごとのプレイヤー人数
+struct HourlyPlayers {
+    hour: i64,
+    samples: i64,
+    players_avg: f64,
+    players_peak: i32,
+}
+
+/// 1時間ごとの集計をまとめる
+/// 合計のサンプル数と集計を返し、空ならNoneを返す
+fn summarize(hours: &[&HourlyPlayers]) -> Option<(i64, PlayerSummary)> {
+    let samples = hours.iter().map(|hour| hour.samples).sum::<i64>();
+    if samples == 0 {
+        return None;
+    }
+
+    let mut averages = hours
+        .iter()
+        .map(|hour| hour.players_avg)
+        .collect::<Vec<_>>();
+    averages.sort_by(f64::total_cmp);
+    let middle = averages.len() / 2;
+    let median = if averages.len() % 2 == 0 {
+        (averages[middle - 1] + averages[middle]) / 2.0
+    } else {
+        averages[middle]
+    };
+
+    let summary = PlayerSummary {
+        average: hours
+            .iter()
+            .map(|hour| hour.players_avg * hour.samples as f64)
+            .sum::<f64>()
+            / samples as f64,
+        peak: hours
+            .iter()
+            .map(|hour| hour.players_peak)
+            .max()
+            .unwrap_or(0),
+        median,
+    };
+    Some((samples, summary))
+}
+
+/// `first` 番から `count` 個の番号付きパラメーター (`?4, ?5, ...`)
+/// `IN (...)` に並べて使う
+fn placeholders(first: usize, count: usize) -> String {
+    (first..first + count)
+        .map(|index| format!("?{index}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// metaテーブルの値 (なければ0)
+fn meta(connection: &Connection, key: &str) -> Result<i64> {
+    let value = connection
+        .query_row(
+            "SELECT value FROM meta WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0);
+    Ok(value)
+}
+
+fn set_meta(connection: &Connection, key: &str, value: i64) -> Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{offline_probe, online_probe};
+
+    /// 日の境界に揃えた基準時刻
+    const T0: i64 = 19676 * DAY;
+
+    fn store() -> SqliteHistoryStore {
+        SqliteHistoryStore::open(
+            ":memory:",
+            RetentionPolicy {
+                raw: DAY,
+                hourly: 3 * DAY,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn raw_history_returns_probes_in_range() {
+        let store = store();
+        store.record(&online_probe("a", T0 + 60, 5, 10)).unwrap();
+        store.record(&offline_probe("a", T0)).unwrap();
+        store.record(&online_probe("a", T0 + 120, 7, 10)).unwrap();
+        store.record(&online_probe("b", T0 + 30, 100, 10)).unwrap();
+
+        let points = store.history("a", T0, T0 + 120, Resolution::Raw).unwrap();
+        let points = points
+            .iter()
+            .map(|point| (point.timestamp, point.players_peak, point.online_ratio))
+            .collect::<Vec<_>>();
+        assert_eq!(points, vec![(T0, 0, 0.0), (T0 + 60, 5, 1.0)]);
+    }
+
+    #[test]
+    fn history_keeps_the_newest_points_over_the_limit() {
+        let store = store();
+        let count = MAX_HISTORY_POINTS + 10;
+        for i in 0..count {
+            store
+                .record(&online_probe("a", T0 + i * 60, 1, 10))
+                .unwrap();
+        }
+
+        let points = store
+            .history("a", T0, T0 + count * 60, Resolution::Raw)
+            .unwrap();
+        assert_eq!(points.len() as i64, MAX_HISTORY_POINTS);
+        assert_eq!(points.first().unwrap().timestamp, T0 + 10 * 60);
+        assert_eq!(points.last().unwrap().timestamp, T0 + (count - 1) * 60);
+    }
+
+    #[test]
+    fn hourly_history_merges_aggregated_and_raw() {
+        let store = store();
+        store.record(&online_probe("a", T0, 10, 10)).unwrap();
+        store.record(&online_probe("a", T0 + 1800, 20, 10)).unwrap();
+        store.record(&offline_probe("a", T0 + HOUR)).unwrap();
+        store
+            .record(&online_probe("a", T0 + HOUR + 60, 30, 10))
+            .unwrap();
+        // 最初の1時間だけが集計済みになる
+        store.compact(T0 + HOUR + 120).unwrap();
+
+        let points = store
+            .history("a", T0, T0 + 2 * HOUR, Resolution::Hour)
+            .unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].timestamp, T0);
+        assert_eq!(points[0].players_avg, 15.0);
+        assert_eq!(points[0].players_peak, 20);
+        assert_eq!(points[0].online_ratio, 1.0);
+        assert_eq!(points[1].timestamp, T0 + HOUR);
+        assert_eq!(points[1].players_avg, 15.0);
+        assert_eq!(points[1].players_peak, 30);
+        assert_eq!(points[1].online_ratio, 0.5);
+
+        let points = store.history("a", T0, T0 + DAY, Resolution::Day).unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].timestamp, T0);
+        assert_eq!(points[0].players_avg, 15.0);
+        assert_eq!(points[0].players_peak, 30);
+        assert_eq!(points[0].online_ratio, 0.75);
+    }
+
+    #[test]
+    fn compact_does_not_aggregate_the_current_hour_twice() {
+        let store = store();
+        store.record(&online_probe("a", T0 + HOUR, 10, 10)).unwrap();
+        // 境界ちょうどのpingはまだ終わっていない時間帯のもの
+        store.compact(T0 + HOUR).unwrap();
+        store
+            .record(&online_probe("a", T0 + HOUR + 60, 20, 10))
+            .unwrap();
+        store.compact(T0 + 2 * HOUR).unwrap();
+        store.compact(T0 + 2 * HOUR + 60).unwrap();
+
+        let points = store
+            .history("a", T0, T0 + 3 * HOUR, Resolution::Hour)
+            .unwrap();
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].timestamp, T0 + HOUR);
+        assert_eq!(points[0].players_avg, 15.0);
+        assert_eq!(points[0].online_ratio, 1.0);
+    }
+
+    #[test]
+    fn compact_removes_data_past_retention() {
+        let store = store();
+        store.record(&online_probe("a", T0, 10, 10)).unwrap();
+        store.record(&online_probe("a", T0 + DAY, 20, 10)).unwrap();
+
+        // 生データは保持期間ちょうどまで残る
+        store.compact(T0 + DAY).unwrap();
+        let raw = store
+            .history("a", T0, T0 + 2 * DAY, Resolution::Raw)
+            .unwrap();
+        assert_eq!(raw.len(), 2);
+
+        // 保持期間を過ぎた生データは1時間ごとの集計としてだけ残る
+        store.compact(T0 + DAY + 1).unwrap();
+        let raw = store
+            .history("a", T0, T0 + 2 * DAY, Resolution::Raw)
+            .unwrap();
+        assert_eq!(raw.len(), 1);
+        assert_eq!(raw[0].timestamp, T0 + DAY);
+        let hourly = store.history("a", T0, T0 + HOUR, Resolution::Hour).unwrap();
+        assert_eq!(hourly.len(), 1);
+        assert_eq!(hourly[0].players_avg, 10.0);
+
+        store.compact(T0 + 3 * DAY).unwrap();
+        let hourly = store.history("a", T0, T0 + HOUR, Resolution::Hour).unwrap();
+        assert_eq!(hourly.len(), 1);
+
+        store.compact(T0 + 3 * DAY + 1).unwrap();
+        let hourly = store.history("a", T0, T0 + HOUR, Resolution::Hour).unwrap();
+        assert!(hourly.is_empty());
+    }
+
+    #[test]
+    fn rollup_summarizes_finished_days() {
+        let store = store();
+        for hour in 0..24 {
+            store
+                .record(&online_probe("a", T0 + hour * HOUR, hour as i32, 10))
+                .unwrap();
+        }
+        store.compact(T0 + DAY).unwrap();
+        store.rollup().unwrap();
+
+        let servers = ["a".to_string(), "b".to_string()];
+        for period in [RollupPeriod::Day, RollupPeriod::Week] {
+            let summaries = store.player_summaries(&servers, period, T0 + DAY).unwrap();
+            // 記録のない "b" は含まれない
+            assert_eq!(summaries.len(), 1);
+            let summary = &summaries["a"];
+            assert_eq!(summary.average, 11.5);
+            assert_eq!(summary.peak, 23);
+            assert_eq!(summary.median, 11.5);
+        }
+        assert!(
+            store
+                .player_summaries(&servers, RollupPeriod::Day, T0 + DAY + 1)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rollup_skips_unfinished_days() {
+        let store = store();
+        store.record(&online_probe("a", T0, 10, 10)).unwrap();
+        store.compact(T0 + DAY - 1).unwrap();
+        store.rollup().unwrap();
+
+        assert!(
+            store
+                .player_summaries(&["a".to_string()], RollupPeriod::Day, T0)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn timelines_merge_aggregated_and_raw_per_server() {
+        let store = store();
+        store.record(&online_probe("a", T0, 10, 10)).unwrap();
+        store.record(&offline_probe("a", T0 + 1800)).unwrap();
+        store.record(&online_probe("b", T0 + 60, 4, 10)).unwrap();
+        // 最初の1時間だけが集計済みになる
+        store.compact(T0 + HOUR + 120).unwrap();
+        store
+            .record(&online_probe("a", T0 + HOUR + 60, 30, 10))
+            .unwrap();
+        store.record(&online_probe("c", T0, 1, 10)).unwrap();
+
+        let servers = ["a".to_string(), "b".to_string()];
+        let timelines = store.timelines(&servers, T0, T0 + 2 * HOUR).unwrap();
+        assert_eq!(timelines.len(), 2);
+        let points = |server: &str| {
+            timelines[server]
+                .iter()
+                .map(|point| (point.timestamp, point.online_ratio, point.players_avg))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(points("a"), vec![(T0, 0.5, 5.0), (T0 + HOUR, 1.0, 30.0)]);
+        assert_eq!(points("b"), vec![(T0, 1.0, 4.0)]);
+    }
+
+    #[test]
+    fn last_online_uses_raw_and_hourly_data() {
+        let store = store();
+        store.record(&online_probe("a", T0, 10, 10)).unwrap();
+        store.record(&online_probe("b", T0 + 60, 10, 10)).unwrap();
+        store.record(&offline_probe("b", T0 + 120)).unwrap();
+        store.record(&offline_probe("c", T0)).unwrap();
+        // "a" の生データは保持期間を過ぎて1時間ごとの集計だけが残る
+        store.compact(T0 + DAY + 1).unwrap();
+        store
+            .record(&online_probe("b", T0 + DAY + 60, 10, 10))
+            .unwrap();
+
+        let servers = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let last_online = store.last_online(&servers).unwrap();
+        assert_eq!(last_online.len(), 2);
+        assert_eq!(last_online["a"], T0 + HOUR - 1);
+        assert_eq!(last_online["b"], T0 + DAY + 60);
+    }
+
+    #[test]
+    fn latency_summary_ignores_offline_probes() {
+        let store = store();
+        store.record(&online_probe("a", T0, 0, 100)).unwrap();
+        store.record(&online_probe("a", T0 + 60, 0, 10)).unwrap();
+        store.record(&offline_probe("a", T0 + 120)).unwrap();
+        store.record(&online_probe("a", T0 + 180, 0, 30)).unwrap();
+        store.record(&online_probe("a", T0 + 240, 0, 20)).unwrap();
+
+        let summary = store.latency_summary("a", T0 + 60).unwrap().unwrap();
+        assert_eq!(summary.min, 10);
+        assert_eq!(summary.max, 30);
+        assert_eq!(summary.average, 20.0);
+        assert_eq!(summary.samples, 3);
+
+        assert!(store.latency_summary("b", T0).unwrap().is_none());
+    }
+
+    #[test]
+    fn register_servers_keeps_the_first_time() {
+        let store = store();
+        let servers = vec!["a".to_string()];
+        store.register_servers(&servers, T0).unwrap();
+
+        let servers = vec!["a".to_string(), "b".to_string()];
+        let added_at = store.register_servers(&servers, T0 + DAY).unwrap();
+        assert_eq!(added_at["a"], T0);
+        assert_eq!(added_at["b"], T0 + DAY);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    config::ServerConfig,
+    health::Health,
+    minecraft::MinecraftServerInfo,
+    ranking::RankingScore,
+    service::MinecraftServerStatus,
+    stats::{PlayerStats, UptimeStats},
+    storage::{PlayerSummary, ProbeRecord, TimelinePoint},
+};
+
+const HOUR: i64 = 60 * 60;
+
+/// `fields` (TOML) に名前・アイコン・説明欄を足したservers.tomlのエントリー1件分
+/// `fields` には少なくともipを書く
+pub fn server_entry(fields: &str) -> String {
+    format!("[[servers]]\n{}\n", server_fields(fields))
+}
+
+/// [`server_entry`] を読み込んだもの
+pub fn server_config(fields: &str) -> ServerConfig {
+    toml::from_str(&server_fields(fields)).unwrap()
+}
+
+fn server_fields(fields: &str) -> String {
+    format!("icon = \"stone\"\nname = \"a\"\ndescription = \"a\"\n{fields}")
+}
+
+/// example.comからのpingの応答
+pub fn server_info(version_name: &str, version_protocol: i32, motd: &str) -> MinecraftServerInfo {
+    MinecraftServerInfo {
+        host: "example.com".to_string(),
+        port_effective: 25565,
+        resolved: "203.0.113.1:25565".parse().unwrap(),
+        connect_ms: 10,
+        rtt_ms: 20,
+        version_name: version_name.to_string(),
+        version_protocol,
+        players_online: 0,
+        players_max: 100,
+        motd: motd.to_string(),
+        motd_legacy: motd.to_string(),
+        motd_json: "{}".to_string(),
+    }
+}
+
+/// `config` のサーバーが `players_online` 人でオンラインのステータス
+pub fn online_status(config: &ServerConfig, players_online: i32) -> MinecraftServerStatus {
+    MinecraftServerStatus {
+        key: config.key(),
+        id: config.id(),
+        ip: config.ip.clone(),
+        port: config.port.unwrap_or(25565) as _,
+        icon: config.icon.clone(),
+        name: config.name.clone(),
+        description: config.description.clone(),
+        is_online: true,
+        health: Health::Online,
+        version_name: "Paper 1.21.4".to_string(),
+        players_online,
+        players_max: 100,
+        offline_reason: None,
+        uptime: UptimeStats::default(),
+        players: PlayerStats::default(),
+        trending_score: None,
+        reading: config.reading.clone(),
+        version_protocol: 769,
+        latency_ms: Some(50),
+        added_at: None,
+        boost: config.boost,
+        ranking: RankingScore::default(),
+        tags: config.tags.clone(),
+        editions: config.editions.clone(),
+        categories: config.categories.clone(),
+        languages: config.languages.clone(),
+        audience: config.audience,
+        links: config.links.clone(),
+        software: Some("Paper"),
+        endpoints: Vec::new(),
+        transferable: None,
+    }
+}
+
+/// `config` のサーバーがオフラインのステータス
+pub fn offline_status(config: &ServerConfig) -> MinecraftServerStatus {
+    MinecraftServerStatus {
+        is_online: false,
+        health: Health::Offline,
+        version_name: "".to_string(),
+        players_online: 0,
+        players_max: 0,
+        version_protocol: 0,
+        latency_ms: None,
+        software: None,
+        transferable: Some(false),
+        ..online_status(config, 0)
+    }
+}
+
+/// `server` に `players` 人で応答したpingの記録
+pub fn online_probe(server: &str, timestamp: i64, players: i32, latency_ms: i64) -> ProbeRecord {
+    ProbeRecord {
+        server: server.to_string(),
+        timestamp,
+        is_online: true,
+        players_online: players,
+        players_max: 100,
+        latency_ms: Some(latency_ms),
+        version_name: Some("Paper 1.21.4".to_string()),
+        offline_reason: None,
+    }
+}
+
+/// `server` に届かなかったpingの記録
+pub fn offline_probe(server: &str, timestamp: i64) -> ProbeRecord {
+    ProbeRecord {
+        server: server.to_string(),
+        timestamp,
+        is_online: false,
+        players_online: 0,
+        players_max: 0,
+        latency_ms: None,
+        version_name: None,
+        offline_reason: Some("Unreachable".to_string()),
+    }
+}
+
+pub fn timeline_point(timestamp: i64, online_ratio: f64, players_avg: f64) -> TimelinePoint {
+    TimelinePoint {
+        timestamp,
+        online_ratio,
+        players_avg,
+    }
+}
+
+/// `from` から `to` の手前まで、ずっとオンラインだった1時間ごとの点
+pub fn hourly_timeline(from: i64, to: i64, players_avg: f64) -> Vec<TimelinePoint> {
+    (from..to)
+        .step_by(HOUR as usize)
+        .map(|timestamp| timeline_point(timestamp, 1.0, players_avg))
+        .collect()
+}
+
+pub fn player_summary(average: f64, peak: i32, median: f64) -> PlayerSummary {
+    PlayerSummary {
+        average,
+        peak,
+        median,
+    }
+}
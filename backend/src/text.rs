@@ -0,0 +1,58 @@
+use icu_normalizer::ComposingNormalizer;
+
+/// Minecraftの書式コード (`§` と続く1文字) を取り除く
+pub fn strip_formatting(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// 検索用に文字列をそろえる
+/// * 書式コードを取り除く
+/// * 全角英数字や半角カタカナなどの幅をNFKCでそろえる
+/// * カタカナをひらがなにする
+/// * 大文字を小文字にする
+pub fn normalize_for_search(text: &str) -> String {
+    let text = ComposingNormalizer::new_nfkc().normalize(&strip_formatting(text));
+    text.chars()
+        .map(katakana_to_hiragana)
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// カタカナを対応するひらがなにする
+/// ひらがなにないもの (ヷ など) はそのまま
+fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' | 'ヽ'..='ヾ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_formatting_codes() {
+        assert_eq!(strip_formatting("§aGreen §lBold§r"), "Green Bold");
+        assert_eq!(strip_formatting("trailing §"), "trailing ");
+    }
+
+    #[test]
+    fn normalizes_width_and_kana() {
+        assert_eq!(normalize_for_search("サバイバル"), "さばいばる");
+        assert_eq!(normalize_for_search("ｻﾊﾞｲﾊﾞﾙ"), "さばいばる");
+        assert_eq!(normalize_for_search("さばいばる"), "さばいばる");
+        assert_eq!(normalize_for_search("ＰｖＰ"), "pvp");
+        assert_eq!(normalize_for_search("§6PvP"), "pvp");
+        assert_eq!(normalize_for_search("鯖"), "鯖");
+    }
+}
@@ -0,0 +1,754 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    net::IpAddr,
+    ops::Range,
+    process::ExitCode,
+};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    address_policy::is_non_public,
+    config::{Edition, LocalizedText, ServerConfig, ServersConfig},
+};
+
+/// 説明欄の最大文字数
+const MAX_DESCRIPTION_CHARS: usize = 200;
+/// 説明欄の最大行数 (GUIのloreに収まる程度)
+const MAX_DESCRIPTION_LINES: usize = 6;
+/// 補正値の絶対値の上限
+const MAX_BOOST: f64 = 1.0;
+/// タグの最大数
+const MAX_TAGS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// エントリーを採用しない
+    Error,
+    /// 採用するが修正が望ましい
+    Warning,
+}
+
+/// servers.tomlの検証結果1件分
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// 1始まりの行番号
+    pub line: usize,
+    /// 1始まりの列番号
+    pub column: usize,
+    /// 対象となるエントリーの1始まりの番号 (ファイル全体に対するものはNone)
+    pub server: Option<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, severity, self.message
+        )
+    }
+}
+
+/// 検証済みのサーバーリスト
+pub struct ValidatedServers {
+    /// エラーのなかったエントリーのみを含む
+    pub config: ServersConfig,
+    /// エラーで除外されたエントリー数
+    pub rejected: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// 位置情報を取るためだけのビュー
+/// スキーマの変更に追従しなくて済むようにフィールドは汎用的に持つ
+#[derive(Deserialize)]
+struct SpannedServersConfig {
+    #[serde(default)]
+    servers: Vec<Spanned<BTreeMap<String, Spanned<toml::Value>>>>,
+}
+
+/// servers.tomlをパースして各エントリーを検証する
+/// TOMLとして読めない場合はそのエラーを返す
+pub fn validate(source: &str) -> Result<ValidatedServers, Diagnostic> {
+    let servers_config: ServersConfig = source.parse().map_err(|error: toml::de::Error| {
+        let (line, column) = error
+            .span()
+            .map(|span| line_column(source, span.start))
+            .unwrap_or((1, 1));
+        Diagnostic {
+            severity: Severity::Error,
+            message: error.message().to_string(),
+            line,
+            column,
+            server: None,
+        }
+    })?;
+    let spanned: SpannedServersConfig = toml::from_str(source).map_err(|error| Diagnostic {
+        severity: Severity::Error,
+        message: error.message().to_string(),
+        line: 1,
+        column: 1,
+        server: None,
+    })?;
+
+    let mut diagnostics = Vec::new();
+    let mut accepted = Vec::new();
+    let mut rejected = 0;
+    // 採用したエントリーだけを登録し、除外したエントリーとの重複は報告しない
+    let mut seen = HashMap::new();
+    let mut seen_ids = HashMap::new();
+    // リスト上の項目のID (ネットワークに属するものはネットワークのID) と、それがネットワークかどうか
+    let mut seen_listings = HashMap::new();
+
+    for (index, (server, spans)) in servers_config
+        .servers
+        .into_iter()
+        .zip(spanned.servers)
+        .enumerate()
+    {
+        let number = index + 1;
+        let mut checker = EntryChecker {
+            source,
+            number,
+            entry_span: spans.span(),
+            fields: spans.get_ref(),
+            diagnostics: Vec::new(),
+        };
+        checker.check(&server);
+
+        // ポートを省略したものはSRVレコードを引くので、ポートを書いたものとは別の接続先になる
+        let key = server.key();
+        if let Some(&(first, line)) = seen.get(&key) {
+            checker.error("ip", format!("duplicate of server #{first} (line {line})"));
+        }
+        let id = server.id();
+        if let Some(&(first, line)) = seen_ids.get(&id) {
+            checker.error(
+                "id",
+                format!("id '{id}' is already used by server #{first} (line {line})"),
+            );
+        }
+        // 単独のエントリーのID同士の重複は上で検出するので、片方がネットワークの場合だけを見る
+        let listing_id = server.network.clone().unwrap_or_else(|| id.clone());
+        if let Some(&(first, line, is_network)) = seen_listings.get(&listing_id)
+            && is_network != server.network.is_some()
+        {
+            let field = if server.network.is_some() {
+                "network"
+            } else {
+                "id"
+            };
+            checker.error(
+                field,
+                format!("'{listing_id}' is already used by server #{first} (line {line})"),
+            );
+        }
+
+        let has_error = checker
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error);
+        diagnostics.append(&mut checker.diagnostics);
+
+        if has_error {
+            rejected += 1;
+        } else {
+            let (line, _) = line_column(source, spans.span().start);
+            seen.insert(key, (number, line));
+            seen_ids.insert(id, (number, line));
+            seen_listings
+                .entry(listing_id)
+                .or_insert((number, line, server.network.is_some()));
+            accepted.push(server);
+        }
+    }
+
+    Ok(ValidatedServers {
+        config: ServersConfig { servers: accepted },
+        rejected,
+        diagnostics,
+    })
+}
+
+struct EntryChecker<'a> {
+    source: &'a str,
+    /// 1始まりのエントリーの番号
+    number: usize,
+    entry_span: Range<usize>,
+    fields: &'a BTreeMap<String, Spanned<toml::Value>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl EntryChecker<'_> {
+    fn check(&mut self, server: &ServerConfig) {
+        self.check_host(server);
+
+        if let Some(id) = &server.id
+            && !is_valid_id(id)
+        {
+            self.error(
+                "id",
+                format!("'{id}' is not a valid id (use a-z, 0-9 and '-')"),
+            );
+        }
+        match (&server.network, &server.player_pool) {
+            (Some(network), _) if !is_valid_id(network) => self.error(
+                "network",
+                format!("'{network}' is not a valid network id (use a-z, 0-9 and '-')"),
+            ),
+            (None, Some(_)) => self.warning(
+                "player_pool",
+                "player_pool has no effect without network".to_string(),
+            ),
+            _ => {}
+        }
+
+        if server.added_at.is_some() && server.added_at_unix().is_none() {
+            self.error(
+                "added_at",
+                "added_at must be a date (e.g. 2024-05-01)".to_string(),
+            );
+        }
+
+        if server.port == Some(0) {
+            self.error("port", "port must not be 0".to_string());
+        }
+        self.check_localized("name", &server.name);
+        if server
+            .name
+            .texts()
+            .iter()
+            .any(|name| name.trim().is_empty())
+        {
+            self.error("name", "name must not be empty".to_string());
+        }
+
+        // GUI側で Material.valueOf(icon.uppercase()) に渡される
+        let icon = server
+            .icon
+            .strip_prefix("minecraft:")
+            .unwrap_or(&server.icon);
+        if icon.is_empty() || !icon.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.error(
+                "icon",
+                format!("'{}' is not a valid item material name", server.icon),
+            );
+        }
+
+        if let Some(reading) = &server.reading {
+            // 漢字が残っていると名前順で読みどおりに並ばない
+            let is_reading_char = |c: char| {
+                matches!(c, 'ぁ'..='ゖ' | 'ァ'..='ヺ' | 'ー' | '・')
+                    || c.is_ascii_alphanumeric()
+                    || c.is_whitespace()
+            };
+            if reading.trim().is_empty() {
+                self.error("reading", "reading must not be empty".to_string());
+            } else if !reading.chars().all(is_reading_char) {
+                self.warning(
+                    "reading",
+                    "reading should be written in kana or alphanumerics".to_string(),
+                );
+            }
+        }
+
+        if !(-MAX_BOOST..=MAX_BOOST).contains(&server.boost) {
+            self.error(
+                "boost",
+                format!("boost must be between -{MAX_BOOST} and {MAX_BOOST}"),
+            );
+        }
+
+        self.check_tags(server);
+        self.check_categories(server);
+        self.check_languages(server);
+        self.check_links(server);
+        if !server.editions.contains(&Edition::Java) {
+            self.error(
+                "editions",
+                "editions must include java (servers are checked with the Java Edition protocol)"
+                    .to_string(),
+            );
+        }
+
+        self.check_localized("description", &server.description);
+        for description in server.description.texts() {
+            let chars = description.chars().count();
+            if chars > MAX_DESCRIPTION_CHARS {
+                self.warning(
+                    "description",
+                    format!(
+                        "description is too long ({chars} > {MAX_DESCRIPTION_CHARS} characters)"
+                    ),
+                );
+            }
+            let lines = description.lines().count();
+            if lines > MAX_DESCRIPTION_LINES {
+                self.warning(
+                    "description",
+                    format!("description has too many lines ({lines} > {MAX_DESCRIPTION_LINES})"),
+                );
+            }
+        }
+    }
+
+    /// 言語ごとのテーブルで書かれた場合のキーを確かめる
+    fn check_localized(&mut self, field: &str, text: &LocalizedText) {
+        let LocalizedText::Localized(texts) = text else {
+            return;
+        };
+        if texts.is_empty() {
+            self.error(field, format!("{field} must have at least one language"));
+            return;
+        }
+        for locale in texts.keys() {
+            if !is_language_code(locale) {
+                self.error(
+                    field,
+                    format!("'{locale}' is not a valid language code (e.g. 'ja', 'en-US')"),
+                );
+            }
+        }
+        if !texts.contains_key(LocalizedText::DEFAULT_LOCALE) {
+            self.warning(
+                field,
+                format!(
+                    "{field} should have '{}' (used when the requested language is missing)",
+                    LocalizedText::DEFAULT_LOCALE
+                ),
+            );
+        }
+    }
+
+    fn check_tags(&mut self, server: &ServerConfig) {
+        for tag in server.tags.iter() {
+            let is_valid = !tag.is_empty()
+                && tag
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !is_valid {
+                self.error(
+                    "tags",
+                    format!("'{tag}' is not a valid tag (use a-z, 0-9 and '-')"),
+                );
+            }
+        }
+        if server.tags.len() > MAX_TAGS {
+            self.warning(
+                "tags",
+                format!("too many tags ({} > {MAX_TAGS})", server.tags.len()),
+            );
+        }
+    }
+
+    fn check_categories(&mut self, server: &ServerConfig) {
+        for (index, category) in server.categories.iter().enumerate() {
+            if server.categories[..index].contains(category) {
+                self.warning(
+                    "categories",
+                    format!(
+                        "category '{}' is listed more than once",
+                        format!("{category:?}").to_lowercase()
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_languages(&mut self, server: &ServerConfig) {
+        for language in server.languages.iter() {
+            if !is_language_code(language) {
+                self.error(
+                    "languages",
+                    format!("'{language}' is not a valid language code (e.g. 'ja', 'en-US')"),
+                );
+            }
+        }
+    }
+
+    fn check_links(&mut self, server: &ServerConfig) {
+        let links = &server.links;
+        if let Some(website) = &links.website {
+            let is_valid = (website.starts_with("https://") || website.starts_with("http://"))
+                && !website.contains(char::is_whitespace);
+            if !is_valid {
+                self.error("links", format!("'{website}' is not a valid website URL"));
+            } else if website.starts_with("http://") {
+                self.warning("links", format!("'{website}' should use https"));
+            }
+        }
+        if let Some(discord) = &links.discord {
+            let code = discord
+                .strip_prefix("https://discord.gg/")
+                .or_else(|| discord.strip_prefix("https://discord.com/invite/"));
+            let is_valid = code.is_some_and(|code| {
+                !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+            if !is_valid {
+                self.error(
+                    "links",
+                    format!("'{discord}' is not a Discord invite URL (https://discord.gg/...)"),
+                );
+            }
+        }
+        if let Some(x) = &links.x {
+            let is_valid = (1..=15).contains(&x.len())
+                && x.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_valid {
+                self.error(
+                    "links",
+                    format!("'{x}' is not a valid X account name (without '@')"),
+                );
+            }
+        }
+    }
+
+    fn check_host(&mut self, server: &ServerConfig) {
+        let host = server.ip.trim();
+        if host.is_empty() {
+            self.error("ip", "ip must not be empty".to_string());
+            return;
+        }
+        if host != server.ip {
+            self.error(
+                "ip",
+                "ip must not contain surrounding whitespace".to_string(),
+            );
+        }
+
+        match host.parse::<IpAddr>() {
+            Ok(ip) => {
+                if is_non_public(ip) {
+                    self.error("ip", format!("{ip} is not a public address"));
+                }
+            }
+            Err(_) => {
+                if host.contains(':') {
+                    self.error(
+                        "ip",
+                        format!("'{host}' contains a port; use the `port` field instead"),
+                    );
+                } else if host.eq_ignore_ascii_case("localhost") {
+                    self.error("ip", "localhost is not a public address".to_string());
+                } else if host.contains(|c: char| c.is_whitespace() || c == '/') {
+                    self.error("ip", format!("'{host}' is not a valid host name"));
+                }
+            }
+        }
+    }
+
+    fn error(&mut self, field: &str, message: String) {
+        self.push(Severity::Error, field, message);
+    }
+
+    fn warning(&mut self, field: &str, message: String) {
+        self.push(Severity::Warning, field, message);
+    }
+
+    fn push(&mut self, severity: Severity, field: &str, message: String) {
+        // フィールドの位置が取れなければエントリーの先頭を指す
+        let offset = self
+            .fields
+            .get(field)
+            .map(|value| value.span().start)
+            .unwrap_or(self.entry_span.start);
+        let (line, column) = line_column(self.source, offset);
+        self.diagnostics.push(Diagnostic {
+            severity,
+            message: format!("server #{}: {}", self.number, message),
+            line,
+            column,
+            server: Some(self.number),
+        });
+    }
+}
+
+/// サーバー・ネットワークのIDとして使える文字列か (英小文字・数字・ハイフン)
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// "ja" や "en-US" の形 (言語コードと、必要なら地域コード) か
+fn is_language_code(text: &str) -> bool {
+    let (code, region) = match text.split_once('-') {
+        Some((code, region)) => (code, Some(region)),
+        None => (text, None),
+    };
+    (2..=3).contains(&code.len())
+        && code.chars().all(|c| c.is_ascii_lowercase())
+        && region.is_none_or(|region| {
+            region.len() == 2 && region.chars().all(|c| c.is_ascii_uppercase())
+        })
+}
+
+/// バイトオフセットを1始まりの行・列に変換する
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// `backend validate <file>` サブコマンド
+/// エラーが1件でもあれば失敗で終了する
+pub fn run_cli(path: Option<&str>) -> ExitCode {
+    let Some(path) = path else {
+        eprintln!("usage: backend validate <servers.toml>");
+        return ExitCode::from(2);
+    };
+
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{path}: error: {error}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let validated = match validate(&source) {
+        Ok(validated) => validated,
+        Err(diagnostic) => {
+            eprintln!("{path}:{diagnostic}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for diagnostic in validated.diagnostics.iter() {
+        eprintln!("{path}:{diagnostic}");
+    }
+
+    let warnings = validated
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+        .count();
+    let errors = validated.diagnostics.len() - warnings;
+    println!(
+        "{path}: {} servers ok, {} rejected, {errors} errors, {warnings} warnings",
+        validated.config.servers.len(),
+        validated.rejected
+    );
+
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Audience, Category},
+        testing::server_entry,
+    };
+
+    #[test]
+    fn accepts_a_valid_entry() {
+        let validated = validate(&server_entry("ip = \"example.com\"")).unwrap();
+        assert_eq!(validated.config.servers.len(), 1);
+        assert_eq!(validated.rejected, 0);
+        assert!(validated.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn reports_toml_errors_with_position() {
+        let error = validate("[[servers]]\nip = \"example.com\nport = 1\n")
+            .err()
+            .unwrap();
+        assert_eq!(error.severity, Severity::Error);
+        assert_eq!(error.line, 2);
+        assert_eq!(error.server, None);
+    }
+
+    #[test]
+    fn points_at_the_offending_field() {
+        let source = server_entry("ip = \"example.com\"\nport = 0");
+        let validated = validate(&source).unwrap();
+        assert_eq!(validated.rejected, 1);
+        let diagnostic = &validated.diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!((diagnostic.line, diagnostic.column), (6, 8));
+        assert_eq!(diagnostic.server, Some(1));
+        assert_eq!(
+            diagnostic.to_string(),
+            "6:8: error: server #1: port must not be 0"
+        );
+    }
+
+    #[test]
+    fn points_at_the_entry_when_the_field_is_missing() {
+        // idを省略したエントリーのIDがネットワークのIDと重なる
+        let source = [
+            server_entry("ip = \"a.example.com\"\nnetwork = \"play\""),
+            "\n".to_string(),
+            server_entry("ip = \"play\""),
+        ]
+        .concat();
+        let validated = validate(&source).unwrap();
+        let diagnostic = &validated.diagnostics[0];
+        assert_eq!(diagnostic.server, Some(2));
+        assert_eq!((diagnostic.line, diagnostic.column), (8, 1));
+    }
+
+    #[test]
+    fn warnings_do_not_reject() {
+        let validated =
+            validate(&server_entry("ip = \"example.com\"\nreading = \"漢字\"")).unwrap();
+        assert_eq!(validated.config.servers.len(), 1);
+        assert_eq!(validated.diagnostics.len(), 1);
+        assert_eq!(validated.diagnostics[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn rejects_duplicate_addresses() {
+        let source = [
+            server_entry("ip = \"example.com\"\nport = 25565"),
+            server_entry("ip = \"Example.com\"\nport = 25565\nid = \"second\""),
+            server_entry("ip = \"example.com\"\nport = 25566\nid = \"third\""),
+        ]
+        .concat();
+        let validated = validate(&source).unwrap();
+        assert_eq!(validated.config.servers.len(), 2);
+        assert_eq!(validated.rejected, 1);
+        let diagnostic = &validated.diagnostics[0];
+        assert_eq!(diagnostic.server, Some(2));
+        assert_eq!(diagnostic.line, 11);
+        assert!(
+            diagnostic
+                .message
+                .ends_with("duplicate of server #1 (line 1)")
+        );
+    }
+
+    #[test]
+    fn omitted_port_is_a_different_address() {
+        // ポートを省略するとSRVレコードで別のポートに向くことがある
+        let source = [
+            server_entry("ip = \"example.com\""),
+            server_entry("ip = \"example.com\"\nport = 25565\nid = \"explicit\""),
+        ]
+        .concat();
+        let validated = validate(&source).unwrap();
+        assert_eq!(validated.config.servers.len(), 2);
+        assert!(validated.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn rejected_entries_do_not_claim_addresses() {
+        let source = [
+            server_entry("ip = \"example.com\"\nport = 25565\nboost = 5.0"),
+            server_entry("ip = \"example.com\"\nport = 25565\nid = \"second\""),
+        ]
+        .concat();
+        let validated = validate(&source).unwrap();
+        assert_eq!(validated.rejected, 1);
+        assert_eq!(validated.config.servers.len(), 1);
+        assert_eq!(validated.config.servers[0].id(), "second");
+        assert_eq!(validated.diagnostics.len(), 1);
+        assert_eq!(validated.diagnostics[0].server, Some(1));
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        let source = [
+            server_entry("ip = \"a.example.com\"\nid = \"same\""),
+            server_entry("ip = \"b.example.com\"\nid = \"same\""),
+            server_entry("ip = \"c.example.com\"\nnetwork = \"same\""),
+        ]
+        .concat();
+        let validated = validate(&source).unwrap();
+        assert_eq!(validated.rejected, 2);
+        assert!(
+            validated.diagnostics[0]
+                .message
+                .ends_with("id 'same' is already used by server #1 (line 1)")
+        );
+        assert!(
+            validated.diagnostics[1]
+                .message
+                .ends_with("'same' is already used by server #1 (line 1)")
+        );
+    }
+
+    #[test]
+    fn language_codes() {
+        assert!(is_language_code("ja"));
+        assert!(is_language_code("en-US"));
+        assert!(!is_language_code("EN"));
+        assert!(!is_language_code("en-us"));
+        assert!(!is_language_code("english"));
+    }
+
+    #[test]
+    fn checks_categories_languages_and_links() {
+        let source = server_entry(
+            "ip = \"example.com\"
+categories = [\"survival\", \"pvp\", \"survival\"]
+languages = [\"ja\", \"Japanese\"]
+links = { website = \"http://example.com\", discord = \"https://example.com/abc\", x = \"@example\" }",
+        );
+        let validated = validate(&source).unwrap();
+        assert_eq!(validated.rejected, 1);
+        let messages = validated
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.message.as_str()))
+            .collect::<Vec<_>>();
+        assert!(messages.contains(&(
+            Severity::Warning,
+            "server #1: category 'survival' is listed more than once"
+        )));
+        assert!(messages.contains(&(
+            Severity::Error,
+            "server #1: 'Japanese' is not a valid language code (e.g. 'ja', 'en-US')"
+        )));
+        assert!(messages.contains(&(
+            Severity::Warning,
+            "server #1: 'http://example.com' should use https"
+        )));
+        assert!(messages.contains(&(
+            Severity::Error,
+            "server #1: 'https://example.com/abc' is not a Discord invite URL (https://discord.gg/...)"
+        )));
+        assert!(messages.contains(&(
+            Severity::Error,
+            "server #1: '@example' is not a valid X account name (without '@')"
+        )));
+    }
+
+    #[test]
+    fn accepts_valid_categories_languages_and_links() {
+        let source = server_entry(
+            "ip = \"example.com\"
+categories = [\"survival\", \"minigames\"]
+languages = [\"ja\", \"en-US\"]
+audience = \"teen\"
+links = { website = \"https://example.com\", discord = \"https://discord.gg/abc-123\", x = \"example_mc\" }",
+        );
+        let validated = validate(&source).unwrap();
+        assert!(validated.diagnostics.is_empty());
+        let server = &validated.config.servers[0];
+        assert_eq!(server.categories, [Category::Survival, Category::Minigames]);
+        assert_eq!(server.audience, Some(Audience::Teen));
+    }
+
+    #[test]
+    fn unknown_category_is_a_toml_error() {
+        let source = server_entry("ip = \"example.com\"\ncategories = [\"farming\"]");
+        assert!(validate(&source).is_err());
+    }
+}
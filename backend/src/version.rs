@@ -0,0 +1,399 @@
+use std::fmt;
+
+/// Minecraftのバージョン番号 (1.21.4 など)
+/// パッチ番号が省略されたものや "x" のものは0として扱う
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GameVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// バージョン名から読み取ったバージョン番号
+#[derive(Debug, Clone, Copy)]
+struct VersionToken {
+    version: GameVersion,
+    /// パッチ番号が "x" (そのマイナーバージョンのすべて)
+    any_patch: bool,
+    /// パッチ番号が省略されている ("1.21" など)
+    patch_omitted: bool,
+}
+
+/// バージョン名に含まれるバージョン番号を順に読み取る
+/// "Paper 1.21.4" や "Velocity 1.8.x-1.21.x" のような表記に対応する
+fn parse_tokens(name: &str) -> Vec<VersionToken> {
+    let mut tokens = Vec::new();
+    let mut rest = name;
+
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        // 他の数字や単語の途中から読み始めない
+        let preceded = name.len() - rest.len() + start;
+        let boundary = name[..preceded]
+            .chars()
+            .next_back()
+            .map(|c| !c.is_ascii_alphanumeric() && c != '.')
+            .unwrap_or(true);
+
+        let candidate = &rest[start..];
+        let end = candidate
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'x' || c == 'X'))
+            .unwrap_or(candidate.len());
+        let token = candidate[..end].trim_end_matches('.');
+        rest = &candidate[end..];
+
+        if !boundary {
+            continue;
+        }
+        let mut parts = token.split('.');
+        let (Some(major), Some(minor)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let (Ok(major), Ok(minor)) = (major.parse(), minor.parse()) else {
+            continue;
+        };
+        let (patch, any_patch, patch_omitted) = match parts.next() {
+            None => (0, false, true),
+            Some("x" | "X") => (0, true, false),
+            Some(patch) => match patch.parse() {
+                Ok(patch) => (patch, false, false),
+                Err(_) => continue,
+            },
+        };
+        tokens.push(VersionToken {
+            version: GameVersion {
+                major,
+                minor,
+                patch,
+            },
+            any_patch,
+            patch_omitted,
+        });
+    }
+
+    tokens
+}
+
+/// バージョン名に含まれるバージョン番号をすべて取り出す
+pub fn parse_versions(name: &str) -> Vec<GameVersion> {
+    parse_tokens(name)
+        .into_iter()
+        .map(|token| token.version)
+        .collect()
+}
+
+/// "1.21.4" のようなバージョン番号を読み取る
+pub fn parse_version(text: &str) -> Option<GameVersion> {
+    parse_tokens(text).first().map(|token| token.version)
+}
+
+/// バージョン名に含まれる最も新しいバージョン
+pub fn latest_version(name: &str) -> Option<GameVersion> {
+    parse_versions(name).into_iter().max()
+}
+
+/// バージョン名から読み取れる対応バージョンの範囲 (両端を含む)
+/// "1.8.x-1.21.x" なら1.8.0から1.21系のすべてまで
+/// 範囲の表記ではパッチ番号の省略も "x" と同じに扱う ("1.8-1.21" は "1.8.x-1.21.x" と同じ)
+pub fn supported_range(name: &str) -> Option<(GameVersion, GameVersion)> {
+    let tokens = parse_tokens(name);
+    let is_range = tokens.len() > 1;
+    let min = tokens.iter().map(|token| token.version).min()?;
+    let max = tokens
+        .iter()
+        .map(|token| GameVersion {
+            patch: if token.any_patch || (is_range && token.patch_omitted) {
+                u32::MAX
+            } else {
+                token.version.patch
+            },
+            ..token.version
+        })
+        .max()?;
+    Some((min, max))
+}
+
+/// 同じプロトコル番号を使うJava Editionのリリース (両端を含む)
+#[derive(Debug, Clone, Copy)]
+pub struct Release {
+    pub protocol: i32,
+    pub first: GameVersion,
+    pub last: GameVersion,
+}
+
+impl Release {
+    /// "1.21.2-1.21.3" のような名前 (1つだけなら "1.21.4")
+    /// パッチ番号が0のものは "1.21" のように書く
+    pub fn name(&self) -> String {
+        let format = |version: GameVersion| match version.patch {
+            0 => format!("{}.{}", version.major, version.minor),
+            _ => version.to_string(),
+        };
+        if self.first == self.last {
+            format(self.first)
+        } else {
+            format!("{}-{}", format(self.first), format(self.last))
+        }
+    }
+}
+
+const fn release(protocol: i32, first: (u32, u32, u32), last: (u32, u32, u32)) -> Release {
+    Release {
+        protocol,
+        first: GameVersion {
+            major: first.0,
+            minor: first.1,
+            patch: first.2,
+        },
+        last: GameVersion {
+            major: last.0,
+            minor: last.1,
+            patch: last.2,
+        },
+    }
+}
+
+/// Transferパケットで転送できる最初のプロトコル番号 (1.20.5)
+pub const TRANSFER_MIN_PROTOCOL: i32 = 766;
+
+/// プロトコル番号とリリースの対応表 (古い順)
+/// 新しいバージョンが出たら末尾に追加する
+pub const RELEASES: [Release; 48] = [
+    release(4, (1, 7, 2), (1, 7, 5)),
+    release(5, (1, 7, 6), (1, 7, 10)),
+    release(47, (1, 8, 0), (1, 8, 9)),
+    release(107, (1, 9, 0), (1, 9, 0)),
+    release(108, (1, 9, 1), (1, 9, 1)),
+    release(109, (1, 9, 2), (1, 9, 2)),
+    release(110, (1, 9, 3), (1, 9, 4)),
+    release(210, (1, 10, 0), (1, 10, 2)),
+    release(315, (1, 11, 0), (1, 11, 0)),
+    release(316, (1, 11, 1), (1, 11, 2)),
+    release(335, (1, 12, 0), (1, 12, 0)),
+    release(338, (1, 12, 1), (1, 12, 1)),
+    release(340, (1, 12, 2), (1, 12, 2)),
+    release(393, (1, 13, 0), (1, 13, 0)),
+    release(401, (1, 13, 1), (1, 13, 1)),
+    release(404, (1, 13, 2), (1, 13, 2)),
+    release(477, (1, 14, 0), (1, 14, 0)),
+    release(480, (1, 14, 1), (1, 14, 1)),
+    release(485, (1, 14, 2), (1, 14, 2)),
+    release(490, (1, 14, 3), (1, 14, 3)),
+    release(498, (1, 14, 4), (1, 14, 4)),
+    release(573, (1, 15, 0), (1, 15, 0)),
+    release(575, (1, 15, 1), (1, 15, 1)),
+    release(578, (1, 15, 2), (1, 15, 2)),
+    release(735, (1, 16, 0), (1, 16, 0)),
+    release(736, (1, 16, 1), (1, 16, 1)),
+    release(751, (1, 16, 2), (1, 16, 2)),
+    release(753, (1, 16, 3), (1, 16, 3)),
+    release(754, (1, 16, 4), (1, 16, 5)),
+    release(755, (1, 17, 0), (1, 17, 0)),
+    release(756, (1, 17, 1), (1, 17, 1)),
+    release(757, (1, 18, 0), (1, 18, 1)),
+    release(758, (1, 18, 2), (1, 18, 2)),
+    release(759, (1, 19, 0), (1, 19, 0)),
+    release(760, (1, 19, 1), (1, 19, 2)),
+    release(761, (1, 19, 3), (1, 19, 3)),
+    release(762, (1, 19, 4), (1, 19, 4)),
+    release(763, (1, 20, 0), (1, 20, 1)),
+    release(764, (1, 20, 2), (1, 20, 2)),
+    release(765, (1, 20, 3), (1, 20, 4)),
+    release(766, (1, 20, 5), (1, 20, 6)),
+    release(767, (1, 21, 0), (1, 21, 1)),
+    release(768, (1, 21, 2), (1, 21, 3)),
+    release(769, (1, 21, 4), (1, 21, 4)),
+    release(770, (1, 21, 5), (1, 21, 5)),
+    release(771, (1, 21, 6), (1, 21, 6)),
+    release(772, (1, 21, 7), (1, 21, 8)),
+    release(773, (1, 21, 9), (1, 21, 10)),
+];
+
+/// バージョンを含むリリースのプロトコル番号
+/// 対応表より新しい1.x系は最新の、古いものは最古のリリースとみなす
+fn protocol_of(version: GameVersion) -> Option<i32> {
+    if version.major != 1 {
+        return None;
+    }
+    RELEASES
+        .iter()
+        .rev()
+        .find(|release| release.first <= version)
+        .or(RELEASES.first())
+        .map(|release| release.protocol)
+}
+
+/// サーバーに参加できるプロトコル番号の範囲 (両端を含む)
+/// 基本はpingで返されたプロトコル番号のみで、
+/// バージョン名に "1.8.x-1.21.x" のような範囲があればそれも含める (プロキシやViaVersionなど)
+pub fn protocol_range(version_name: &str, version_protocol: i32) -> (i32, i32) {
+    let detected = supported_range(version_name)
+        .filter(|(min, max)| min != max)
+        .and_then(|(min, max)| Some((protocol_of(min)?, protocol_of(max)?)));
+    match detected {
+        Some((min, max)) => (min.min(version_protocol), max.max(version_protocol)),
+        None => (version_protocol, version_protocol),
+    }
+}
+
+/// バージョン名に現れるサーバーソフトウェアの名前
+/// 派生元より先に派生先を調べる (PurpurはPaperの派生など)
+const KNOWN_SOFTWARE: [&str; 14] = [
+    "Velocity",
+    "Waterfall",
+    "BungeeCord",
+    "Folia",
+    "Purpur",
+    "Pufferfish",
+    "Paper",
+    "Spigot",
+    "CraftBukkit",
+    "NeoForge",
+    "Forge",
+    "Fabric",
+    "Quilt",
+    "Geyser",
+];
+
+/// バージョン名からサーバーソフトウェアを推定する
+/// バージョン番号だけのものはバニラとみなす
+pub fn detect_software(version_name: &str) -> Option<&'static str> {
+    let lower = version_name.to_lowercase();
+    if let Some(software) = KNOWN_SOFTWARE
+        .iter()
+        .find(|software| lower.contains(&software.to_lowercase()))
+    {
+        return Some(software);
+    }
+
+    let is_version_only = !version_name.trim().is_empty()
+        && version_name
+            .trim()
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.');
+    is_version_only.then_some("Vanilla")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(major: u32, minor: u32, patch: u32) -> GameVersion {
+        GameVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    #[test]
+    fn parses_versions_in_names() {
+        assert_eq!(parse_versions("Paper 1.21.4"), vec![version(1, 21, 4)]);
+        assert_eq!(
+            parse_versions("Velocity 1.8.x-1.21.x"),
+            vec![version(1, 8, 0), version(1, 21, 0)]
+        );
+        assert_eq!(
+            parse_versions("BungeeCord 1.8, 1.20.6."),
+            vec![version(1, 8, 0), version(1, 20, 6)]
+        );
+        assert_eq!(parse_versions("1.20"), vec![version(1, 20, 0)]);
+    }
+
+    #[test]
+    fn ignores_numbers_that_are_not_versions() {
+        assert!(parse_versions("Build 123").is_empty());
+        assert!(parse_versions("v1.21.4").is_empty());
+        assert_eq!(parse_version("latest"), None);
+    }
+
+    #[test]
+    fn latest_version_in_a_range() {
+        assert_eq!(
+            latest_version("Velocity 1.8.x-1.21.x"),
+            Some(version(1, 21, 0))
+        );
+        assert_eq!(latest_version("Maintenance"), None);
+    }
+
+    #[test]
+    fn detects_software() {
+        assert_eq!(detect_software("Paper 1.21.4"), Some("Paper"));
+        assert_eq!(detect_software("Purpur 1.21.4"), Some("Purpur"));
+        assert_eq!(detect_software("1.21.4"), Some("Vanilla"));
+        assert_eq!(detect_software("§cMaintenance"), None);
+    }
+
+    #[test]
+    fn supported_range_of_names() {
+        assert_eq!(
+            supported_range("Paper 1.21.4"),
+            Some((version(1, 21, 4), version(1, 21, 4)))
+        );
+        assert_eq!(
+            supported_range("Velocity 1.8.x-1.21.x"),
+            Some((version(1, 8, 0), version(1, 21, u32::MAX)))
+        );
+        assert_eq!(
+            supported_range("1.20.4-1.21.4"),
+            Some((version(1, 20, 4), version(1, 21, 4)))
+        );
+        assert_eq!(supported_range("Maintenance"), None);
+    }
+
+    #[test]
+    fn single_version_without_patch_is_exact() {
+        assert_eq!(
+            supported_range("Paper 1.21"),
+            Some((version(1, 21, 0), version(1, 21, 0)))
+        );
+    }
+
+    #[test]
+    fn patchless_range_end_covers_all_patches() {
+        assert_eq!(
+            supported_range("Velocity 1.8-1.21"),
+            Some((version(1, 8, 0), version(1, 21, u32::MAX)))
+        );
+        assert_eq!(
+            protocol_range("Velocity 1.8-1.21", 773),
+            protocol_range("Velocity 1.8.x-1.21.x", 773)
+        );
+    }
+
+    #[test]
+    fn protocol_range_of_names() {
+        assert_eq!(protocol_range("Paper 1.21.4", 769), (769, 769));
+        assert_eq!(protocol_range("Paper 1.21", 767), (767, 767));
+        assert_eq!(protocol_range("Velocity 1.8.x-1.21.x", 773), (47, 773));
+        assert_eq!(protocol_range("1.20.4-1.21.4", 769), (765, 769));
+        // 名前にない範囲でもpingで返されたプロトコル番号は含める
+        assert_eq!(protocol_range("Velocity 1.8.x-1.20.x", 769), (47, 769));
+        assert_eq!(protocol_range("Maintenance", -1), (-1, -1));
+    }
+
+    #[test]
+    fn protocol_of_versions_outside_the_table() {
+        assert_eq!(protocol_of(version(1, 99, 0)), Some(773));
+        assert_eq!(protocol_of(version(1, 0, 0)), Some(4));
+        assert_eq!(protocol_of(version(2, 0, 0)), None);
+    }
+
+    #[test]
+    fn releases_are_ordered() {
+        for pair in RELEASES.windows(2) {
+            assert!(pair[0].protocol < pair[1].protocol);
+            assert!(pair[0].last < pair[1].first);
+        }
+        assert!(RELEASES.iter().all(|release| release.first <= release.last));
+        assert_eq!(RELEASES[0].name(), "1.7.2-1.7.5");
+        assert_eq!(RELEASES.last().unwrap().name(), "1.21.9-1.21.10");
+        let release_1_21 = RELEASES.iter().find(|release| release.protocol == 767);
+        assert_eq!(release_1_21.unwrap().name(), "1.21-1.21.1");
+    }
+}
@@ -16,17 +16,116 @@ class Ok<out V>(val value: V) : Result<V, Nothing>()
 class Err<out E>(val error: E) : Result<Nothing, E>()
 /**
  * サーバーリストの順序
+ * 括弧内はdirectionを省略したときの向き
+ * 同順位のサーバーは一定時間ごとに入れ替わる順に並ぶ
  */
 @Serializable
 enum class Ordering {
     /**
-     * プレイヤーの多い順
+     * プレイヤー人数 (多い順)
      */
     @SerialName("Player") PLAYER,
     /**
-     * プレイヤーの少ない順
+     * 直近30日間の稼働率 (高い順)
      */
-    @SerialName("PlayerReverse") PLAYERREVERSE,
+    @SerialName("Uptime") UPTIME,
+    /**
+     * 7日間の平均プレイヤー人数 (多い順)
+     */
+    @SerialName("WeeklyAveragePlayers") WEEKLYAVERAGEPLAYERS,
+    /**
+     * 勢い (ある順)
+     */
+    @SerialName("Trending") TRENDING,
+    /**
+     * 人数・平均人数・稼働率・応答時間などを合わせたスコア (高い順)
+     */
+    @SerialName("Ranked") RANKED,
+    /**
+     * 一定時間ごとに入れ替わるランダムな順
+     * 同じ時間帯の中では誰が取得しても同じ順になる
+     */
+    @SerialName("Shuffle") SHUFFLE,
+    /**
+     * 名前 (五十音順・アルファベット順)
+     * 読みが登録されていれば読みで並べる
+     */
+    @SerialName("Name") NAME,
+    /**
+     * pingの応答時間 (速い順)
+     */
+    @SerialName("Latency") LATENCY,
+    /**
+     * 対応している最も新しいバージョン (新しい順)
+     */
+    @SerialName("Version") VERSION,
+    /**
+     * サーバーリストに追加された日時 (新しい順)
+     */
+    @SerialName("RecentlyAdded") RECENTLYADDED,
+}
+
+/**
+ * 並び替えの向き
+ * 基準となる値がないサーバー (オフラインで応答時間がない等) は向きに関係なく最後になる
+ */
+@Serializable
+enum class Direction {
+    /**
+     * 小さい順・古い順・五十音順
+     */
+    @SerialName("Ascending") ASCENDING,
+    /**
+     * 大きい順・新しい順・五十音の逆順
+     */
+    @SerialName("Descending") DESCENDING,
+}
+
+/**
+ * サーバーのジャンル
+ */
+@Serializable
+enum class Category {
+    @SerialName("Survival") SURVIVAL,
+    @SerialName("Minigames") MINIGAMES,
+    @SerialName("Pvp") PVP,
+    @SerialName("Rpg") RPG,
+    @SerialName("Creative") CREATIVE,
+    @SerialName("Modded") MODDED,
+}
+
+/**
+ * 想定する年齢層
+ */
+@Serializable
+enum class Audience {
+    /**
+     * 全年齢
+     */
+    @SerialName("Everyone") EVERYONE,
+    /**
+     * 13歳以上
+     */
+    @SerialName("Teen") TEEN,
+    /**
+     * 18歳以上
+     */
+    @SerialName("Adult") ADULT,
+}
+
+/**
+ * Minecraftのエディション
+ */
+@Serializable
+enum class Edition {
+    /**
+     * Java版
+     */
+    @SerialName("Java") JAVA,
+    /**
+     * 統合版 (Geyser等で参加できるサーバー)
+     */
+    @SerialName("Bedrock") BEDROCK,
 }
 
 /**
@@ -45,6 +144,7 @@ data class Server (
     /**
      * サーバーの説明欄
      * 改行可
+     * 言語ごとに書き分けられている場合はlocaleで指定した言語のもの
      */
     @SerialName("description") val description: String,
     /**
@@ -55,6 +155,11 @@ data class Server (
      * オンラインかどうか
      */
     @SerialName("is_online") val isOnline: Boolean,
+    /**
+     * 稼働状態
+     * 再確認中(Suspect)のサーバーはis_onlineがtrueのまま
+     */
+    @SerialName("health") val health: Health,
     /**
      * MinecraftサーバーのIPアドレス
      */
@@ -65,14 +170,509 @@ data class Server (
     @SerialName("port") val port: Long,
     /**
      * サーバーの名前
+     * 言語ごとに書き分けられている場合はlocaleで指定した言語のもの
      */
     @SerialName("name") val name: String,
     /**
      * 最大プレイ人数
      */
     @SerialName("players_max") val playersMax: Long,
+    /**
+     * オフラインと判定された理由
+     */
+    @SerialName("offline_reason") val offlineReason: OfflineReason? = null,
+    /**
+     * 直近30日間の稼働率 (0.0〜1.0)
+     */
+    @SerialName("uptime_30d") val uptime30d: Double? = null,
+    /**
+     * 直近30日間で最も長い停止時間 (秒)
+     */
+    @SerialName("longest_outage") val longestOutage: Long,
+    /**
+     * 直近24時間の稼働率 (0.0〜1.0)
+     * 履歴がなければnull
+     */
+    @SerialName("uptime_24h") val uptime24h: Double? = null,
+    /**
+     * 勢い
+     * 直近24時間の平均人数が、その前の7日間の平均人数からどれだけ伸びたか (負なら減少)
+     * 履歴が足りなければnull
+     */
+    @SerialName("trending_score") val trendingScore: Double? = null,
+    /**
+     * 最後にオンラインだった時刻 (UNIX時間, 秒)
+     */
+    @SerialName("last_seen_online") val lastSeenOnline: Long? = null,
+    /**
+     * 直近7日間の稼働率 (0.0〜1.0)
+     */
+    @SerialName("uptime_7d") val uptime7d: Double? = null,
+    /**
+     * 直近30日間の平均故障間隔 (秒)
+     * 停止がなければnull
+     */
+    @SerialName("mtbf") val mtbf: Long? = null,
+    /**
+     * 前日 (UTC) のプレイヤー人数
+     * 集計がなければnull
+     */
+    @SerialName("players_daily") val playersDaily: PlayerStats? = null,
+    /**
+     * 前日までの7日間のプレイヤー人数
+     */
+    @SerialName("players_weekly") val playersWeekly: PlayerStats? = null,
+    /**
+     * Rankedの順序のスコアとその内訳
+     */
+    @SerialName("ranking") val ranking: RankingScore,
+    /**
+     * サーバーリストに追加された時刻 (UNIX時間, 秒)
+     * servers.tomlに追加日が書かれていなければ、このAPIのサーバーが初めて確認した時刻 (実際に追加された時刻より遅いことがある)
+     */
+    @SerialName("added_at") val addedAt: Long? = null,
+    /**
+     * pingの往復時間 (ミリ秒)
+     * オフラインならnull
+     */
+    @SerialName("latency") val latency: Long? = null,
+    /**
+     * 参加できるエディション
+     */
+    @SerialName("editions") val editions: List<Edition>,
+    /**
+     * バージョン名から推定したサーバーソフトウェア (Paper, Velocity, Vanilla など)
+     * 推定できなければnull
+     */
+    @SerialName("software") val software: String? = null,
+    /**
+     * タグ
+     */
+    @SerialName("tags") val tags: List<String>,
+    /**
+     * サーバーのID
+     * servers.tomlで指定されていなければホスト名とポートから作られる
+     */
+    @SerialName("id") val id: String,
+    /**
+     * 外部のページへのリンク
+     */
+    @SerialName("links") val links: Links,
+    /**
+     * ネットワークの入口ごとの状態
+     * ネットワークでは、ip・port・バージョンは転送先に選ばれた入口 (is_bestがtrue) のもの
+     * プレイヤー人数は入口ごとの合計で、プレイヤーを共有する入口は重複して数えない
+     * ネットワークに属さないサーバーでは空
+     */
+    @SerialName("endpoints") val endpoints: List<Endpoint>,
+    /**
+     * Transferパケットで転送できるか
+     * 1.20.5以降に対応していて、転送を受け付ける (accepts-transfers=true) ならtrue
+     * 設定で転送を実際に確かめている場合はその結果、そうでなければservers.tomlの宣言に従う
+     * 1.20.5より前のバージョンやオンラインでないもの、転送を受け付けないと分かっているものはfalse
+     * どちらとも分からなければnull (転送を試してよい)
+     */
+    @SerialName("transferable") val transferable: Boolean? = null,
+    /**
+     * client_protocolで指定したプロトコル番号のクライアントが参加できるか
+     * pingで返されたプロトコル番号と、バージョン名から読み取れる範囲 ("1.8.x-1.21.x" など) から判断する
+     * オフラインならfalse、client_protocolを指定していなければnull
+     */
+    @SerialName("compatible") val compatible: Boolean? = null,
+    /**
+     * 対応している言語 ("ja", "en-US" など)
+     */
+    @SerialName("languages") val languages: List<String>,
+    /**
+     * 想定する年齢層
+     * 指定されていなければnull
+     */
+    @SerialName("audience") val audience: Audience? = null,
+    /**
+     * ジャンル
+     */
+    @SerialName("categories") val categories: List<Category>,
+)
+
+/**
+ * ネットワークの入口1つ分の状態
+ */
+@Serializable
+data class Endpoint (
+    /**
+     * 最大プレイ人数
+     */
+    @SerialName("players_max") val playersMax: Long,
+    /**
+     * 転送先に選ばれたか
+     * 稼働状態が良く、pingの往復時間が短いものが選ばれる
+     */
+    @SerialName("is_best") val isBest: Boolean,
+    /**
+     * Transferパケットで転送できるか
+     * どちらとも分からなければnull
+     */
+    @SerialName("transferable") val transferable: Boolean? = null,
+    /**
+     * client_protocolで指定したプロトコル番号のクライアントが参加できるか
+     * client_protocolを指定していなければnull
+     */
+    @SerialName("compatible") val compatible: Boolean? = null,
+    /**
+     * 入口のポート
+     */
+    @SerialName("port") val port: Long,
+    /**
+     * 入口の名前
+     * 言語ごとに書き分けられている場合はlocaleで指定した言語のもの
+     */
+    @SerialName("name") val name: String,
+    /**
+     * pingの往復時間 (ミリ秒)
+     * オフラインならnull
+     */
+    @SerialName("latency") val latency: Long? = null,
+    /**
+     * 稼働状態
+     */
+    @SerialName("health") val health: Health,
+    /**
+     * 参加できるエディション
+     */
+    @SerialName("editions") val editions: List<Edition>,
+    /**
+     * バージョン名
+     */
+    @SerialName("version_name") val versionName: String,
+    /**
+     * オンラインかどうか
+     */
+    @SerialName("is_online") val isOnline: Boolean,
+    /**
+     * プレイヤー人数
+     */
+    @SerialName("players_online") val playersOnline: Long,
+    /**
+     * 入口のIPアドレス
+     */
+    @SerialName("ip") val ip: String,
+)
+
+/**
+ * プロトコル番号と、それを使うJava Editionのリリース
+ */
+@Serializable
+data class ProtocolVersion (
+    /**
+     * リリース名 ("1.21.4", "1.21.2-1.21.3" など)
+     */
+    @SerialName("name") val name: String,
+    /**
+     * プロトコル番号
+     */
+    @SerialName("protocol") val protocol: Long,
+)
+
+/**
+ * 外部のページへのリンク
+ */
+@Serializable
+data class Links (
+    /**
+     * Xのアカウント名 (@なし)
+     */
+    @SerialName("x") val x: String? = null,
+    /**
+     * WebサイトのURL
+     */
+    @SerialName("website") val website: String? = null,
+    /**
+     * Discordの招待URL
+     */
+    @SerialName("discord") val discord: String? = null,
 )
 
+/**
+ * サーバーリストの1ページ
+ */
+@Serializable
+data class ServerListPage (
+    /**
+     * 指定したcursorのスナップショットが既に破棄されているか、cursorが不正か、最初のページと引数が異なる
+     * スナップショットは公開から5分間保持する
+     * このときserversは空なので、最初のページから取得し直す
+     */
+    @SerialName("cursor_expired") val cursorExpired: Boolean,
+    /**
+     * 続きを取得するためのカーソル
+     * 続きがなければnull
+     */
+    @SerialName("next_cursor") val nextCursor: String? = null,
+    /**
+     * このページのサーバー
+     */
+    @SerialName("servers") val servers: List<Server>,
+    /**
+     * ページを切り出したスナップショットの番号
+     */
+    @SerialName("generation") val generation: Long,
+    /**
+     * 絞り込み後の全件数
+     */
+    @SerialName("total") val total: Long,
+)
+
+/**
+ * Rankedの順序のスコア
+ * 各要素にバックエンドの設定の重みを掛けたもので、合計がtotalになる
+ */
+@Serializable
+data class RankingScore (
+    /**
+     * pingの応答の速さ
+     */
+    @SerialName("latency") val latency: Double,
+    /**
+     * 合計
+     */
+    @SerialName("total") val total: Double,
+    /**
+     * 現在のプレイヤー人数 (対数)
+     */
+    @SerialName("players") val players: Double,
+    /**
+     * servers.tomlで指定された補正値
+     */
+    @SerialName("boost") val boost: Double,
+    /**
+     * 直近30日間の稼働率
+     */
+    @SerialName("uptime") val uptime: Double,
+    /**
+     * 7日間の平均プレイヤー人数 (対数)
+     */
+    @SerialName("average_players") val averagePlayers: Double,
+)
+
+/**
+ * ある期間のプレイヤー人数の集計
+ */
+@Serializable
+data class PlayerStats (
+    /**
+     * 最大プレイヤー人数
+     */
+    @SerialName("peak") val peak: Long,
+    /**
+     * 平均プレイヤー人数
+     */
+    @SerialName("average") val average: Double,
+    /**
+     * 1時間ごとの平均人数の中央値
+     */
+    @SerialName("median") val median: Double,
+)
+
+/**
+ * サーバーの稼働状態
+ */
+@Serializable
+enum class Health {
+    /**
+     * 直前のpingに成功した
+     */
+    @SerialName("Online") ONLINE,
+    /**
+     * pingに失敗し、オフラインかどうか再確認中
+     */
+    @SerialName("Suspect") SUSPECT,
+    /**
+     * 失敗が続いたためオフラインと判定した
+     */
+    @SerialName("Offline") OFFLINE,
+    /**
+     * pingには応答するがメンテナンス中 (is_onlineはfalse)
+     */
+    @SerialName("Maintenance") MAINTENANCE,
+}
+
+/**
+ * オフラインと判定された理由
+ */
+@Serializable
+enum class OfflineReason {
+    /**
+     * 名前解決・接続・応答のいずれかに失敗した
+     */
+    @SerialName("Unreachable") UNREACHABLE,
+    /**
+     * 接続先が内部ネットワーク等のアドレスのため接続しなかった
+     */
+    @SerialName("Blocked") BLOCKED,
+}
+
+/**
+ * サーバーの詳細
+ * ネットワークでは、motd・latency_stats・probe・historyは最初の入口 (endpointsの先頭) のもの
+ */
+@Serializable
+data class ServerDetail (
+    /**
+     * 直近7日間の1日ごとのプレイヤー人数とオンライン状態の推移
+     */
+    @SerialName("history") val history: List<HistoryPoint>,
+    /**
+     * 最後に成功したpingのMOTD
+     * 一度も成功していなければnull
+     */
+    @SerialName("motd") val motd: Motd? = null,
+    @SerialName("server") val server: Server,
+    /**
+     * pingの状況
+     */
+    @SerialName("probe") val probe: ProbeDiagnostics,
+    /**
+     * 直近24時間のpingの往復時間
+     * 記録がなければnull
+     */
+    @SerialName("latency_stats") val latencyStats: LatencyStats? = null,
+)
+
+/**
+ * pingで返されたMOTD
+ */
+@Serializable
+data class Motd (
+    /**
+     * 書式を書式コード (§) で表したもの
+     */
+    @SerialName("legacy") val legacy: String,
+    /**
+     * 書式を取り除いたもの
+     */
+    @SerialName("plain") val plain: String,
+    /**
+     * サーバーが返したJSONそのまま
+     */
+    @SerialName("json") val json: String,
+)
+
+/**
+ * pingの往復時間の集計 (ミリ秒)
+ */
+@Serializable
+data class LatencyStats (
+    @SerialName("max") val max: Long,
+    /**
+     * 集計したpingの回数
+     */
+    @SerialName("samples") val samples: Long,
+    @SerialName("min") val min: Long,
+    @SerialName("average") val average: Double,
+)
+
+/**
+ * pingの状況
+ */
+@Serializable
+data class ProbeDiagnostics (
+    /**
+     * 最後に転送を受け付けるかを実際に確かめた時刻 (UNIX時間, 秒)
+     */
+    @SerialName("transfer_checked_at") val transferCheckedAt: Long? = null,
+    /**
+     * 最後に転送を受け付けるかを実際に確かめた結果
+     * 確かめていないか、判断できなければnull
+     */
+    @SerialName("accepts_transfers") val acceptsTransfers: Boolean? = null,
+    /**
+     * 連続で失敗した回数
+     */
+    @SerialName("consecutive_failures") val consecutiveFailures: Long,
+    /**
+     * 最後に成功したpingの接続先 (IP:ポート)
+     */
+    @SerialName("resolved_address") val resolvedAddress: String? = null,
+    /**
+     * 次にpingする予定の時刻 (UNIX時間, 秒)
+     */
+    @SerialName("next_probe_at") val nextProbeAt: Long,
+    /**
+     * 最後に失敗したpingのエラーメッセージ
+     * 成功すると消える
+     */
+    @SerialName("last_error") val lastError: String? = null,
+    /**
+     * 最後にpingした時刻 (UNIX時間, 秒)
+     */
+    @SerialName("last_probe_at") val lastProbeAt: Long? = null,
+    /**
+     * 最後に成功したpingの接続にかかった時間 (ミリ秒)
+     */
+    @SerialName("connect_ms") val connectMs: Long? = null,
+)
+
+/**
+ * 検索結果の1件
+ */
+@Serializable
+data class SearchHit (
+    /**
+     * 関連度 (大きいほど関連が強い)
+     */
+    @SerialName("score") val score: Double,
+    /**
+     * 一致したサーバー
+     */
+    @SerialName("server") val server: Server,
+)
+
+/**
+ * プレイヤー人数の推移の1区間
+ */
+@Serializable
+data class HistoryPoint (
+    /**
+     * 区間内の最大プレイヤー人数
+     */
+    @SerialName("players_peak") val playersPeak: Long,
+    /**
+     * 区間の開始時刻 (UNIX時間, 秒)
+     */
+    @SerialName("timestamp") val timestamp: Long,
+    /**
+     * 区間内でオンラインだった割合 (0.0〜1.0)
+     */
+    @SerialName("online_ratio") val onlineRatio: Double,
+    /**
+     * 区間内で一度でもオンラインだったか
+     */
+    @SerialName("is_online") val isOnline: Boolean,
+    /**
+     * 区間内の平均プレイヤー人数
+     */
+    @SerialName("players_online") val playersOnline: Double,
+)
+
+/**
+ * 履歴の区間の長さ
+ */
+@Serializable
+enum class Resolution {
+    /**
+     * pingごと (直近7日間のみ)
+     */
+    @SerialName("Raw") RAW,
+    /**
+     * 1時間ごと
+     */
+    @SerialName("Hour") HOUR,
+    /**
+     * 1日ごと (UTC)
+     */
+    @SerialName("Day") DAY,
+}
+
 
 
 /**
@@ -80,13 +680,147 @@ data class Server (
 class API(private val url: String) {
     /**
      * サーバーリストを取得する
-     * 配列の順序はorderingに準拠する
+     * 配列の順序はordering・directionに準拠する
+     * directionを省略した場合はorderingごとの既定の向きになる
+     * 残りの引数は絞り込みの条件で、指定したものをすべて満たすサーバーのみを返す
+     * * online_only : trueならオンラインのサーバーのみ
+     * * min_players・max_players : プレイヤー人数の範囲 (両端を含む)
+     * * version : 対応バージョンの範囲にこのバージョン ("1.21.4" など) を含む (バージョンとして読み取れない場合はこの条件を無視する)
+     * * protocol : プロトコル番号が一致する
+     * * edition : このエディションで参加できる
+     * * tags : すべてのタグを持つ
+     * * software : サーバーソフトウェアが一致する (大文字小文字は区別しない)
+     * * category : このジャンルを含む
+     * * language : この言語に対応している ("ja" なら "ja-JP" も含む)
+     * * audience : 想定する年齢層が一致する
+     * limitを指定すると先頭からlimit件だけを返し、続きはnext_cursorをcursorに指定して取得する
+     * cursorを指定したときは最初のページと同じスナップショットから返すので、途中で更新されても重複や抜けが起きない
+     * cursorを指定するときはlimit以外の引数を最初のページと同じにすること (異なる場合はcursor_expiredになる)
+     * localeを指定するとサーバーの名前・説明欄をその言語 ("en", "en-US" など) で返す (なければ既定の言語)
+     * client_protocolにクライアントのプロトコル番号を指定すると、各サーバーに参加できるかをcompatibleで返す
      * 定期的に更新するならキャッシュしても問題ない
      */
-    fun getServerList(ordering: Ordering): List<Server> {
+    fun getServerList(ordering: Ordering, direction: Direction? = null, onlineOnly: Boolean? = null, minPlayers: Long? = null, maxPlayers: Long? = null, version: String? = null, protocol: Long? = null, edition: Edition? = null, tags: List<String>? = null, software: String? = null, category: Category? = null, language: String? = null, audience: Audience? = null, locale: String? = null, clientProtocol: Long? = null, limit: Long? = null, cursor: String? = null): ServerListPage {
         val client = OkHttpClient()
         val url = "${this.url}/api/get_server_list".toHttpUrl().newBuilder()
             .addQueryParameter("ordering", Json.encodeToString(ordering))
+            .addQueryParameter("direction", direction?.let { Json.encodeToString(it) })
+            .addQueryParameter("online_only", onlineOnly?.let { Json.encodeToString(it) })
+            .addQueryParameter("min_players", minPlayers?.let { Json.encodeToString(it) })
+            .addQueryParameter("max_players", maxPlayers?.let { Json.encodeToString(it) })
+            .addQueryParameter("version", version?.let { Json.encodeToString(it) })
+            .addQueryParameter("protocol", protocol?.let { Json.encodeToString(it) })
+            .addQueryParameter("edition", edition?.let { Json.encodeToString(it) })
+            .addQueryParameter("tags", tags?.let { Json.encodeToString(it) })
+            .addQueryParameter("software", software?.let { Json.encodeToString(it) })
+            .addQueryParameter("category", category?.let { Json.encodeToString(it) })
+            .addQueryParameter("language", language?.let { Json.encodeToString(it) })
+            .addQueryParameter("audience", audience?.let { Json.encodeToString(it) })
+            .addQueryParameter("locale", locale?.let { Json.encodeToString(it) })
+            .addQueryParameter("client_protocol", clientProtocol?.let { Json.encodeToString(it) })
+            .addQueryParameter("limit", limit?.let { Json.encodeToString(it) })
+            .addQueryParameter("cursor", cursor?.let { Json.encodeToString(it) })
+            .build()
+        val reqBody = ""
+            .toRequestBody("application/json".toMediaType())
+        val request = Request.Builder()
+            .url(url)
+            .get()
+            .build()
+        client.newCall(request).execute().use { response ->
+            if (!response.isSuccessful) {
+                error("HTTP ${response.code}: ${response.body?.string()}")
+            }
+            return Json.decodeFromString(response.body!!.string())
+        }
+    }
+    /**
+     * サーバーのプレイヤー人数とオンライン状態の推移を取得する
+     * idはServerの値を指定する (ネットワークはget_serverと同じエントリーの推移になる)
+     * from以上to未満の範囲を古い順に返す
+     * 点が多すぎる場合は新しい方から5000個までを返す
+     * 該当するサーバーがなければ空の配列を返す
+     */
+    fun getServerHistory(id: String, from: Long, to: Long, resolution: Resolution): List<HistoryPoint> {
+        val client = OkHttpClient()
+        val url = "${this.url}/api/get_server_history".toHttpUrl().newBuilder()
+            .addQueryParameter("id", Json.encodeToString(id))
+            .addQueryParameter("from", Json.encodeToString(from))
+            .addQueryParameter("to", Json.encodeToString(to))
+            .addQueryParameter("resolution", Json.encodeToString(resolution))
+            .build()
+        val reqBody = ""
+            .toRequestBody("application/json".toMediaType())
+        val request = Request.Builder()
+            .url(url)
+            .get()
+            .build()
+        client.newCall(request).execute().use { response ->
+            if (!response.isSuccessful) {
+                error("HTTP ${response.code}: ${response.body?.string()}")
+            }
+            return Json.decodeFromString(response.body!!.string())
+        }
+    }
+    /**
+     * サーバーの名前・読み・説明欄を検索する
+     * queryは空白で区切った語をすべて含むものに一致する
+     * ひらがなとカタカナ、全角と半角、大文字と小文字は区別しない
+     * 関連度の高い順に返し、同じ関連度ならorderingの既定の向きの順にする
+     * limitを指定すると先頭からlimit件だけを返す
+     * localeを指定するとサーバーの名前・説明欄をその言語 ("en", "en-US" など) で返す (なければ既定の言語)
+     */
+    fun searchServers(query: String, ordering: Ordering, limit: Long? = null, locale: String? = null): List<SearchHit> {
+        val client = OkHttpClient()
+        val url = "${this.url}/api/search_servers".toHttpUrl().newBuilder()
+            .addQueryParameter("query", Json.encodeToString(query))
+            .addQueryParameter("ordering", Json.encodeToString(ordering))
+            .addQueryParameter("limit", limit?.let { Json.encodeToString(it) })
+            .addQueryParameter("locale", locale?.let { Json.encodeToString(it) })
+            .build()
+        val reqBody = ""
+            .toRequestBody("application/json".toMediaType())
+        val request = Request.Builder()
+            .url(url)
+            .get()
+            .build()
+        client.newCall(request).execute().use { response ->
+            if (!response.isSuccessful) {
+                error("HTTP ${response.code}: ${response.body?.string()}")
+            }
+            return Json.decodeFromString(response.body!!.string())
+        }
+    }
+    /**
+     * IDを指定してサーバーの詳細を取得する
+     * localeを指定するとサーバーの名前・説明欄をその言語 ("en", "en-US" など) で返す (なければ既定の言語)
+     * 該当するサーバーがなければnull
+     */
+    fun getServer(id: String, locale: String? = null): ServerDetail? {
+        val client = OkHttpClient()
+        val url = "${this.url}/api/get_server".toHttpUrl().newBuilder()
+            .addQueryParameter("id", Json.encodeToString(id))
+            .addQueryParameter("locale", locale?.let { Json.encodeToString(it) })
+            .build()
+        val reqBody = ""
+            .toRequestBody("application/json".toMediaType())
+        val request = Request.Builder()
+            .url(url)
+            .get()
+            .build()
+        client.newCall(request).execute().use { response ->
+            if (!response.isSuccessful) {
+                error("HTTP ${response.code}: ${response.body?.string()}")
+            }
+            return Json.decodeFromString(response.body!!.string())
+        }
+    }
+    /**
+     * プロトコル番号とリリース名の対応表を古い順に取得する
+     */
+    fun getProtocolVersions(): List<ProtocolVersion> {
+        val client = OkHttpClient()
+        val url = "${this.url}/api/get_protocol_versions".toHttpUrl().newBuilder()
             .build()
         val reqBody = ""
             .toRequestBody("application/json".toMediaType())
@@ -21,7 +21,7 @@ class EventListener : Listener {
         if (sign.getSide(Side.FRONT).getLine(2) == "[ Server List ]"
             || sign.getSide(Side.BACK).getLine(2) == "[ Server List ]"
         ) {
-            ServerListGUIRegistry.PLAYERS_ORDER.open(player)
+            ServerListGUIRegistry.get(ServerListOrder.PLAYERS, ServerListLocale.of(player)).open(player)
         }
     }
 }
\ No newline at end of file
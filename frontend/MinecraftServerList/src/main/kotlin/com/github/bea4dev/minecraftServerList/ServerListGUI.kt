@@ -6,22 +6,25 @@ import com.github.bea4dev.artgui.menu.ArtMenu
 import org.bukkit.Material
 import org.bukkit.Sound
 import org.bukkit.entity.Player
-import org.lang.tyml.Ordering
 import kotlin.math.max
 
 object ServerListGUIRegistry {
-    val PLAYERS_ORDER = ServerListGUI(Ordering.PLAYER)
-    val PLAYERS_REVERSE_ORDER = ServerListGUI(Ordering.PLAYERREVERSE)
+    private val guis = ServerListOrder.entries
+        .flatMap { order -> ServerListLocale.entries.map { locale -> Pair(order, locale) } }
+        .associateWith { (order, locale) -> ServerListGUI(order, locale) }
+
+    fun get(order: ServerListOrder, locale: ServerListLocale): ServerListGUI = guis.getValue(Pair(order, locale))
 
     fun init() {
         ServerListService.onUpdate {
-            PLAYERS_ORDER.initGUI()
-            PLAYERS_REVERSE_ORDER.initGUI()
+            for (gui in guis.values) {
+                gui.initGUI()
+            }
         }
     }
 }
 
-class ServerListGUI(private val ordering: Ordering) {
+class ServerListGUI(private val order: ServerListOrder, private val locale: ServerListLocale) {
     private var artMenu: ArtMenu
 
     init {
@@ -59,24 +62,14 @@ class ServerListGUI(private val ordering: Ordering) {
             )
 
             // サーバー順序切り替え
-            val R = when (ordering) {
-                Ordering.PLAYER -> ArtButton(
-                    ItemBuilder(Material.LEVER).name("&r人数の少ない順 | Reverse order").build()
-                ).listener { event, _ ->
-                    val player = event.whoClicked as? Player ?: return@listener
-                    player.playSound(player.location, Sound.UI_BUTTON_CLICK, 1.0F, 2.0F)
-                    player.closeInventory()
-                    ServerListGUIRegistry.PLAYERS_REVERSE_ORDER.open(player)
-                }
-
-                Ordering.PLAYERREVERSE -> ArtButton(
-                    ItemBuilder(Material.LEVER).name("&r人数の多い順 | Players count order").build()
-                ).listener { event, _ ->
-                    val player = event.whoClicked as? Player ?: return@listener
-                    player.playSound(player.location, Sound.UI_BUTTON_CLICK, 1.0F, 2.0F)
-                    player.closeInventory()
-                    ServerListGUIRegistry.PLAYERS_ORDER.open(player)
-                }
+            val nextOrder = order.next()
+            val R = ArtButton(
+                ItemBuilder(Material.LEVER).name("&r${nextOrder.label}").build()
+            ).listener { event, _ ->
+                val player = event.whoClicked as? Player ?: return@listener
+                player.playSound(player.location, Sound.UI_BUTTON_CLICK, 1.0F, 2.0F)
+                player.closeInventory()
+                ServerListGUIRegistry.get(nextOrder, locale).open(player)
             }
 
             arrayOf(
@@ -94,16 +87,9 @@ class ServerListGUI(private val ordering: Ordering) {
 
         // 非同期でアイテムを配置
         artMenu.asyncCreate { menu ->
-            val serverList = when (ordering) {
-                Ordering.PLAYER -> ServerListService.serverListPlayersOrder
-                Ordering.PLAYERREVERSE -> ServerListService.serverListPlayersReverseOrder
-            }
+            val serverList = ServerListService.serverList(order, locale)
 
             for (server in serverList) {
-                if (!server.isOnline) {
-                    continue
-                }
-
                 val icon = try {
                     Material.valueOf(server.icon.uppercase())
                 } catch (_: Exception) {
@@ -114,6 +100,10 @@ class ServerListGUI(private val ordering: Ordering) {
 
                 val description = mutableListOf("&r&7${server.versionName}", "")
                 description.addAll(server.description.split("\n").map { line -> "&r$line" })
+                // 不明 (null) のサーバーは転送を試す
+                if (server.transferable == false) {
+                    description.addAll(listOf("", "&r&cこのサーバーには移動できません | Transfer not available"))
+                }
 
                 menu.addButton(
                     ArtButton(
@@ -125,6 +115,12 @@ class ServerListGUI(private val ordering: Ordering) {
                     ).listener { event, _ ->
                         val player = event.whoClicked as? Player ?: return@listener
 
+                        // 転送を受け付けない (1.20.5より前のバージョンを含む) と分かっているサーバーに送ると切断されてしまうので送らない
+                        if (server.transferable == false) {
+                            player.playSound(player.location, Sound.ENTITY_VILLAGER_NO, 1.0F, 1.0F)
+                            return@listener
+                        }
+
                         // クリック時にTransferパケットで別サーバーに転送する
                         player.transfer(server.ip, server.port.toInt())
                     }
@@ -0,0 +1,21 @@
+package com.github.bea4dev.minecraftServerList
+
+import org.bukkit.entity.Player
+
+/**
+ * サーバーの名前・説明欄を表示する言語
+ * バックエンドにはcodeをlocaleとして渡す
+ */
+enum class ServerListLocale(val code: String) {
+    JA("ja"),
+    EN("en");
+
+    companion object {
+        /**
+         * プレイヤーのクライアントの言語設定 ("ja_jp" など) に合うもの
+         * 日本語以外は英語にする
+         */
+        fun of(player: Player): ServerListLocale =
+            if (player.locale.lowercase().startsWith("ja")) JA else EN
+    }
+}
@@ -0,0 +1,24 @@
+package com.github.bea4dev.minecraftServerList
+
+import org.lang.tyml.Direction
+import org.lang.tyml.Ordering
+
+/**
+ * GUIで切り替えられる並び順
+ * レバーを押すと定義順に次の並び順へ切り替わる
+ */
+enum class ServerListOrder(val ordering: Ordering, val direction: Direction?, val label: String) {
+    PLAYERS(Ordering.PLAYER, null, "人数の多い順 | Players count order"),
+    PLAYERS_REVERSE(Ordering.PLAYER, Direction.ASCENDING, "人数の少ない順 | Reverse order"),
+    UPTIME(Ordering.UPTIME, null, "稼働率の高い順 | Uptime order"),
+    WEEKLY_AVERAGE(Ordering.WEEKLYAVERAGEPLAYERS, null, "週間平均人数の多い順 | Weekly average order"),
+    TRENDING(Ordering.TRENDING, null, "勢いのある順 | Trending order"),
+    RANKED(Ordering.RANKED, null, "おすすめ順 | Ranked order"),
+    SHUFFLE(Ordering.SHUFFLE, null, "ランダム | Shuffle"),
+    NAME(Ordering.NAME, null, "名前順 | Name order"),
+    LATENCY(Ordering.LATENCY, null, "応答の速い順 | Latency order"),
+    VERSION(Ordering.VERSION, null, "新しいバージョン順 | Version order"),
+    RECENTLY_ADDED(Ordering.RECENTLYADDED, null, "新着順 | Recently added order");
+
+    fun next(): ServerListOrder = entries[(ordinal + 1) % entries.size]
+}
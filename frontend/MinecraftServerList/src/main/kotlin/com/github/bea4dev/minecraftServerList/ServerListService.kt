@@ -1,18 +1,24 @@
 package com.github.bea4dev.minecraftServerList
 
 import org.lang.tyml.API
-import org.lang.tyml.Ordering
 import org.lang.tyml.Server
 import java.lang.Thread.sleep
 import java.time.Duration
 import java.util.concurrent.CopyOnWriteArrayList
 
+/**
+ * 一度に取得するサーバーの件数
+ */
+private const val PAGE_SIZE = 100L
+
+/**
+ * カーソルが切れたときに最初から取得し直す回数の上限
+ */
+private const val MAX_RESTARTS = 3
+
 object ServerListService {
     private lateinit var api: API
-    var serverListPlayersOrder = listOf<Server>()
-        private set
-    var serverListPlayersReverseOrder = listOf<Server>()
-        private set
+    private var serverLists = mapOf<Pair<ServerListOrder, ServerListLocale>, List<Server>>()
     private val onUpdate = CopyOnWriteArrayList<Runnable>()
 
     fun init(url: String) {
@@ -22,8 +28,9 @@ object ServerListService {
         Thread {
             while (true) {
                 try {
-                    serverListPlayersOrder = api.getServerList(Ordering.PLAYER)
-                    serverListPlayersReverseOrder = api.getServerList(Ordering.PLAYERREVERSE)
+                    serverLists = ServerListOrder.entries
+                        .flatMap { order -> ServerListLocale.entries.map { locale -> Pair(order, locale) } }
+                        .associateWith { (order, locale) -> fetchServerList(order, locale) }
                 } catch (error: Exception) {
                     MinecraftServerList.plugin.logger.warning("Failed to get server list!")
                     error.printStackTrace()
@@ -37,6 +44,41 @@ object ServerListService {
         }.start()
     }
 
+    /**
+     * ページを順にたどってサーバーリストを取得する
+     * 途中でカーソルが切れたら最初から取得し直す
+     * 上限の回数を超えて切れた場合は諦めて例外を投げる (前回のリストを使い続ける)
+     */
+    private fun fetchServerList(order: ServerListOrder, locale: ServerListLocale): List<Server> {
+        val servers = mutableListOf<Server>()
+        var cursor: String? = null
+        var restarts = 0
+        while (true) {
+            // GUIにはオンラインのサーバーのみを表示する
+            val page = api.getServerList(
+                order.ordering,
+                order.direction,
+                onlineOnly = true,
+                locale = locale.code,
+                limit = PAGE_SIZE,
+                cursor = cursor,
+            )
+            if (page.cursorExpired) {
+                if (++restarts > MAX_RESTARTS) {
+                    error("Cursor expired $restarts times while fetching the server list")
+                }
+                servers.clear()
+                cursor = null
+                continue
+            }
+            servers += page.servers
+            cursor = page.nextCursor ?: return servers
+        }
+    }
+
+    fun serverList(order: ServerListOrder, locale: ServerListLocale): List<Server> =
+        serverLists[Pair(order, locale)] ?: listOf()
+
     fun onUpdate(task: Runnable) {
         onUpdate.add(task)
     }